@@ -0,0 +1,244 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// Opt-in normalization that runs after tracker stripping, so the same page
+// always copies as the same string. Every step keeps the destination: the
+// authority and empty `?`/`#` are pure syntax, identical key=value pairs are
+// redundant, and `index.html` is served as the same document as its
+// directory. Sorting keys is the one step a server could in theory notice,
+// and a trailing `/amp` is only the same page on sites that publish AMP
+// versions (`/products/amp` elsewhere is a page of its own), so both stay
+// off unless asked for.
+// Each bool maps to a `[canonicalize]` key users toggle independently.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Canonicalize {
+    pub sort_query: bool,
+    pub dedupe_query: bool,
+    pub strip_index: bool,
+    pub strip_amp: bool,
+}
+
+impl Default for Canonicalize {
+    fn default() -> Self {
+        Self {
+            sort_query: false,
+            dedupe_query: true,
+            strip_index: true,
+            strip_amp: false,
+        }
+    }
+}
+
+impl fmt::Display for Canonicalize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<&str> = [
+            ("sort_query", self.sort_query),
+            ("dedupe_query", self.dedupe_query),
+            ("strip_index", self.strip_index),
+            ("strip_amp", self.strip_amp),
+        ]
+        .into_iter()
+        .filter_map(|(step, on)| on.then_some(step))
+        .collect();
+        if steps.is_empty() {
+            write!(f, "on")
+        } else {
+            write!(f, "on ({})", steps.join(", "))
+        }
+    }
+}
+
+const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
+
+// Normalizes `url` in place. `original` is the text the URL was parsed from:
+// `Url::parse` already lowercases the host and drops a default port, so the
+// only way to tell those apart is to look at what was copied. Returns true
+// when the canonical form differs from `original`.
+pub fn canonicalize(original: &str, url: &mut Url, config: &Canonicalize) -> bool {
+    let mut changed = authority_differs(original, url);
+
+    if url.query() == Some("") {
+        url.set_query(None);
+        changed = true;
+    }
+    if url.fragment() == Some("") {
+        url.set_fragment(None);
+        changed = true;
+    }
+
+    if let Some(query) = url.query() {
+        let canonical = canonical_query(query, config);
+        if canonical != query {
+            url.set_query((!canonical.is_empty()).then_some(canonical.as_str()));
+            changed = true;
+        }
+    }
+
+    let path = url.path();
+    let mut trimmed = path;
+    if config.strip_index {
+        if let Some(dir) = INDEX_FILES
+            .iter()
+            .find_map(|file| trimmed.strip_suffix(file))
+            .filter(|dir| dir.ends_with('/'))
+        {
+            trimmed = dir;
+        }
+    }
+    if config.strip_amp {
+        // A bare `/amp` is a page of its own, only a trailing segment is
+        // the AMP variant of its parent.
+        if let Some(parent) = trimmed
+            .strip_suffix("/amp/")
+            .or_else(|| trimmed.strip_suffix("/amp"))
+            .filter(|parent| !parent.is_empty())
+        {
+            trimmed = parent;
+        }
+    }
+    if trimmed.len() != path.len() {
+        let trimmed = trimmed.to_string();
+        url.set_path(&trimmed);
+        changed = true;
+    }
+
+    changed
+}
+
+// Works on the raw `&`-separated segments so values keep their original
+// encoding. Only exact duplicates are collapsed: `tag=a&tag=b` is a list,
+// and picking one value would change what the server sees.
+fn canonical_query(query: &str, config: &Canonicalize) -> String {
+    let mut segments: Vec<&str> = query.split('&').filter(|s| !s.is_empty()).collect();
+    if config.dedupe_query {
+        let mut seen = std::collections::HashSet::new();
+        segments.retain(|s| seen.insert(*s));
+    }
+    if config.sort_query {
+        // Stable, so repeated keys keep their relative order.
+        segments.sort_by_key(|s| s.split_once('=').map_or(*s, |(key, _)| key));
+    }
+    segments.join("&")
+}
+
+fn authority_differs(original: &str, url: &Url) -> bool {
+    let Some((_, rest)) = original.split_once("://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    if host_port.bytes().any(|b| b.is_ascii_uppercase()) {
+        return true;
+    }
+    match (url.port(), url.port_or_known_default()) {
+        (None, Some(default)) => host_port.ends_with(&format!(":{default}")),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str, config: &Canonicalize) -> (String, bool) {
+        let mut url = Url::parse(input).unwrap();
+        let changed = canonicalize(input, &mut url, config);
+        (url.to_string(), changed)
+    }
+
+    #[test]
+    fn lowercases_host_and_drops_default_port() {
+        let config = Canonicalize::default();
+        assert_eq!(
+            run("https://Example.COM:443/Path", &config),
+            ("https://example.com/Path".to_string(), true)
+        );
+        assert_eq!(
+            run("http://example.com:8080/", &config),
+            ("http://example.com:8080/".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn removes_empty_query_and_fragment() {
+        assert_eq!(
+            run("https://example.com/a?#", &Canonicalize::default()),
+            ("https://example.com/a".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn collapses_only_identical_pairs() {
+        assert_eq!(
+            run(
+                "https://example.com/?a=1&b=2&a=1&&tag=x&tag=y",
+                &Canonicalize::default()
+            ),
+            ("https://example.com/?a=1&b=2&tag=x&tag=y".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn sorts_keys_when_asked() {
+        let config = Canonicalize {
+            sort_query: true,
+            ..Canonicalize::default()
+        };
+        assert_eq!(
+            run("https://example.com/?z=1&a=2&m=3&a=1", &config),
+            ("https://example.com/?a=2&a=1&m=3&z=1".to_string(), true)
+        );
+        assert!(!run("https://example.com/?z=1&a=2", &Canonicalize::default()).1);
+    }
+
+    #[test]
+    fn strips_index_and_amp_suffixes() {
+        let config = Canonicalize {
+            strip_amp: true,
+            ..Canonicalize::default()
+        };
+        assert_eq!(
+            run("https://example.com/docs/index.html", &config),
+            ("https://example.com/docs/".to_string(), true)
+        );
+        assert_eq!(
+            run("https://example.com/news/story/amp/?id=1", &config),
+            ("https://example.com/news/story?id=1".to_string(), true)
+        );
+        assert_eq!(
+            run("https://example.com/amp", &config),
+            ("https://example.com/amp".to_string(), false)
+        );
+        assert_eq!(
+            run("https://example.com/myindex.html", &config),
+            ("https://example.com/myindex.html".to_string(), false)
+        );
+        assert_eq!(
+            run("https://example.com/products/amp", &Canonicalize::default()),
+            ("https://example.com/products/amp".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn steps_can_be_switched_off() {
+        let config = Canonicalize {
+            sort_query: false,
+            dedupe_query: false,
+            strip_index: false,
+            strip_amp: false,
+        };
+        assert_eq!(
+            run("https://example.com/index.html?a=1&a=1", &config),
+            ("https://example.com/index.html?a=1&a=1".to_string(), false)
+        );
+        assert_eq!(config.to_string(), "on");
+        assert_eq!(
+            Canonicalize::default().to_string(),
+            "on (dedupe_query, strip_index)"
+        );
+    }
+}
@@ -22,19 +22,17 @@ struct ClearUrlsProvider {
     #[serde(default)]
     redirections: Vec<String>,
     #[serde(default)]
-    #[allow(dead_code)]
     raw_rules: Vec<String>,
     #[serde(default)]
     exceptions: Vec<String>,
     #[serde(default)]
-    #[allow(dead_code)]
     force_redirection: bool,
 }
 
 pub struct TranslationResult {
     pub providers: HashMap<String, ProviderConfig>,
     pub rules_translated: usize,
-    pub complete_providers_skipped: usize,
+    pub complete_providers: usize,
 }
 
 pub fn translate(json: &str) -> Result<TranslationResult, String> {
@@ -43,12 +41,11 @@ pub fn translate(json: &str) -> Result<TranslationResult, String> {
 
     let mut providers = HashMap::new();
     let mut rules_translated = 0usize;
-    let mut complete_providers_skipped = 0usize;
+    let mut complete_providers = 0usize;
 
     for (name, cu_provider) in &data.providers {
         if cu_provider.complete_provider {
-            complete_providers_skipped += 1;
-            continue;
+            complete_providers += 1;
         }
 
         let mut rules: Vec<String> = Vec::new();
@@ -74,6 +71,10 @@ pub fn translate(json: &str) -> Result<TranslationResult, String> {
                 rules,
                 redirections: cu_provider.redirections.clone(),
                 exceptions: cu_provider.exceptions.clone(),
+                raw_rules: cu_provider.raw_rules.clone(),
+                force_redirection: cu_provider.force_redirection.then_some(true),
+                complete_provider: cu_provider.complete_provider.then_some(true),
+                ..ProviderConfig::default()
             },
         );
     }
@@ -81,7 +82,7 @@ pub fn translate(json: &str) -> Result<TranslationResult, String> {
     Ok(TranslationResult {
         providers,
         rules_translated,
-        complete_providers_skipped,
+        complete_providers,
     })
 }
 
@@ -173,7 +174,7 @@ mod tests {
     }
 
     #[test]
-    fn skips_complete_providers() {
+    fn carries_complete_providers() {
         let json = r#"{
             "providers": {
                 "blocked": {
@@ -189,14 +190,19 @@ mod tests {
             }
         }"#;
         let result = translate(json).unwrap();
-        assert!(result.providers.is_empty());
+        let provider = &result.providers["blocked"];
+        assert_eq!(provider.complete_provider, Some(true));
+        assert_eq!(
+            provider.url_pattern.as_deref(),
+            Some(r"^https?://blocked\.com")
+        );
+        assert_eq!(provider.rules, vec!["should_be_skipped".to_string()]);
     }
 
-    // Users who expected, say, facebook rules from upstream and got nothing
-    // shouldn't have to dig through the source — `clink update` should report
-    // the count so they know the gap is upstream-by-design, not a bug.
+    // `clink update` reports how many ad/tracking domains came along so users
+    // know `complete_provider_action` has something to act on.
     #[test]
-    fn translate_reports_complete_providers_skipped_count() {
+    fn translate_reports_complete_providers_count() {
         let json = r#"{
             "providers": {
                 "blocked1": {
@@ -232,8 +238,9 @@ mod tests {
             }
         }"#;
         let result = translate(json).unwrap();
-        assert_eq!(result.complete_providers_skipped, 2);
-        assert_eq!(result.providers.len(), 1);
+        assert_eq!(result.complete_providers, 2);
+        assert_eq!(result.providers.len(), 3);
+        assert!(result.providers["ok"].complete_provider.is_none());
     }
 
     #[test]
@@ -264,6 +271,51 @@ mod tests {
         assert_eq!(provider.exceptions[0], r"^https?://youtube\.com/redirect");
     }
 
+    #[test]
+    fn translates_raw_rules() {
+        let json = r#"{
+            "providers": {
+                "amazon": {
+                    "urlPattern": "^https?://amazon\\.com",
+                    "completeProvider": false,
+                    "rules": ["psc"],
+                    "referralMarketing": [],
+                    "rawRules": ["\\/ref=[^\\/?]*"],
+                    "exceptions": [],
+                    "redirections": [],
+                    "forceRedirection": false
+                }
+            }
+        }"#;
+        let result = translate(json).unwrap();
+        let provider = &result.providers["amazon"];
+        assert_eq!(provider.raw_rules, vec![r"\/ref=[^\/?]*".to_string()]);
+        assert_eq!(
+            result.rules_translated, 1,
+            "raw rules are carried separately, not counted as param rules"
+        );
+    }
+
+    #[test]
+    fn translates_force_redirection() {
+        let json = r#"{
+            "providers": {
+                "fb": {
+                    "urlPattern": "^https?://l\\.facebook\\.com",
+                    "completeProvider": false,
+                    "rules": [],
+                    "referralMarketing": [],
+                    "rawRules": [],
+                    "exceptions": [],
+                    "redirections": ["^https?://l\\.facebook\\.com/l\\.php\\?u=([^&]+)"],
+                    "forceRedirection": true
+                }
+            }
+        }"#;
+        let result = translate(json).unwrap();
+        assert_eq!(result.providers["fb"].force_redirection, Some(true));
+    }
+
     #[test]
     fn multiple_providers_stay_separate() {
         let json = r#"{
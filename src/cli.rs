@@ -1,5 +1,9 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::history::ExportFormat;
+use crate::mode::Mode;
 
 #[derive(Parser)]
 #[command(
@@ -23,7 +27,11 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Run the clipboard monitor daemon
-    Run,
+    Run {
+        /// Only log what would be cleaned, leaving the clipboard untouched
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Initialize default config file
     Init,
     /// Install as a system service (launchd on macOS, systemd on Linux)
@@ -38,6 +46,40 @@ pub enum Command {
     Restart,
     /// Show current state and last log entries
     State,
+    /// Stop cleaning the clipboard until `clink resume`
+    Pause {
+        /// Resume on its own after this long, e.g. 90s, 10m, 1h30m
+        #[arg(long = "for", value_name = "DURATION", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Start cleaning the clipboard again after `clink pause`
+    Resume,
+    /// Put the original of the last clipboard rewrite back
+    Undo {
+        /// Also add the hosts of the rewritten links as provider exceptions
+        #[arg(long)]
+        exclude: bool,
+    },
+    /// List, search, copy back or export the history of cleaned links
+    History {
+        /// Only show entries whose original or cleaned text contains this
+        search: Option<String>,
+        /// How many entries to list, newest first
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// Put the cleaned text of entry N, as numbered in the list, back on the clipboard
+        #[arg(long, value_name = "N")]
+        copy: Option<usize>,
+        /// With --copy, put back the original instead of the cleaned text
+        #[arg(long, requires = "copy")]
+        original: bool,
+        /// Print every matching entry as JSON or CSV
+        #[arg(long, value_enum, conflicts_with = "copy")]
+        export: Option<ExportFormat>,
+        /// Delete the history file
+        #[arg(long, conflicts_with_all = ["search", "copy", "export"])]
+        clear: bool,
+    },
     /// Show config info
     Config {
         /// Show differences between current config and defaults
@@ -47,6 +89,23 @@ pub enum Command {
         #[arg(long)]
         reset: bool,
     },
+    /// Clean URLs given as arguments, or stdin line by line, and print the result
+    Clean {
+        /// URLs or text to clean. Reads stdin when omitted.
+        urls: Vec<String>,
+        /// Override the mode from the config file
+        #[arg(long, value_enum)]
+        mode: Option<Mode>,
+    },
+    /// Show which providers and rules apply to each URL
+    Explain {
+        /// URLs (or text containing URLs) to explain
+        #[arg(required = true)]
+        urls: Vec<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Fetch and cache remote patterns
     Update {
         /// Write translated patterns to the given path instead of the cache directory.
@@ -56,6 +115,49 @@ pub enum Command {
     },
 }
 
+// Longer than this is what `clink pause` without `--for` is for.
+const MAX_PAUSE_DAYS: u64 = 365;
+
+// One or more `<number><unit>` parts with units s, m, h and d, so `10m` and
+// `1h30m` both work.
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(format!("unknown unit '{c}', use s, m, h or d")),
+        };
+        let n: u64 = digits
+            .parse()
+            .map_err(|_| format!("expected a number before '{c}'"))?;
+        total = n
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or("duration is too long")?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!("'{digits}' needs a unit, e.g. {digits}m"));
+    }
+    if total == 0 {
+        return Err("duration must be longer than zero, e.g. 10m".to_string());
+    }
+    if total > MAX_PAUSE_DAYS * 24 * 60 * 60 {
+        return Err(format!(
+            "duration must be at most {MAX_PAUSE_DAYS}d; leave out --for to pause until `clink resume`"
+        ));
+    }
+    Ok(Duration::from_secs(total))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,7 +179,6 @@ mod tests {
     #[test]
     fn test_parse_all_subcommands() {
         for (arg, expected) in [
-            ("run", "Run"),
             ("init", "Init"),
             ("install", "Install"),
             ("uninstall", "Uninstall"),
@@ -85,6 +186,7 @@ mod tests {
             ("reload", "Reload"),
             ("restart", "Restart"),
             ("state", "State"),
+            ("resume", "Resume"),
         ] {
             let cli = Cli::parse_from(["clink", arg]);
             assert_eq!(format!("{:?}", cli.command.unwrap()), expected);
@@ -100,6 +202,83 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_pause_for() {
+        let cli = Cli::parse_from(["clink", "pause"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Pause { duration: None })
+        ));
+        let cli = Cli::parse_from(["clink", "pause", "--for", "1h30m"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Pause { duration: Some(d) }) if d == Duration::from_secs(5400)
+        ));
+        assert!(Cli::try_parse_from(["clink", "pause", "--for", "10"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_undo() {
+        let cli = Cli::parse_from(["clink", "undo"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Undo { exclude: false })
+        ));
+        let cli = Cli::parse_from(["clink", "undo", "--exclude"]);
+        assert!(matches!(cli.command, Some(Command::Undo { exclude: true })));
+    }
+
+    #[test]
+    fn test_parse_history() {
+        let cli = Cli::parse_from(["clink", "history", "amazon", "--export", "csv"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::History {
+                search: Some(s),
+                limit: 20,
+                copy: None,
+                original: false,
+                export: Some(ExportFormat::Csv),
+                clear: false,
+            }) if s == "amazon"
+        ));
+        let cli = Cli::parse_from(["clink", "history", "--copy", "2", "--original"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::History {
+                copy: Some(2),
+                original: true,
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["clink", "history", "--original"]).is_err());
+        assert!(Cli::try_parse_from(["clink", "history", "--clear", "--copy", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1d2h"), Ok(Duration::from_secs(93_600)));
+        assert_eq!(
+            parse_duration("365d"),
+            Ok(Duration::from_secs(365 * 24 * 60 * 60))
+        );
+        for bad in [
+            "",
+            "0m",
+            "10",
+            "m",
+            "5x",
+            "1.5h",
+            "365d1s",
+            "100000000d",
+            "99999999999999999999s",
+        ] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+    }
+
     #[test]
     fn test_parse_global_config() {
         let cli = Cli::parse_from(["clink", "--config", "/tmp/c.toml", "init"]);
@@ -118,12 +297,18 @@ mod tests {
     fn test_parse_verbose_global() {
         let cli = Cli::parse_from(["clink", "--verbose", "run"]);
         assert!(cli.verbose);
-        assert!(matches!(cli.command, Some(Command::Run)));
+        assert!(matches!(cli.command, Some(Command::Run { dry_run: false })));
 
         let cli = Cli::parse_from(["clink", "run", "--verbose"]);
         assert!(cli.verbose);
     }
 
+    #[test]
+    fn test_parse_run_dry_run() {
+        let cli = Cli::parse_from(["clink", "run", "--dry-run"]);
+        assert!(matches!(cli.command, Some(Command::Run { dry_run: true })));
+    }
+
     #[test]
     fn test_parse_config_diff() {
         let cli = Cli::parse_from(["clink", "config", "--diff"]);
@@ -148,6 +333,50 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_clean_stdin() {
+        let cli = Cli::parse_from(["clink", "clean"]);
+        match cli.command {
+            Some(Command::Clean { urls, mode }) => {
+                assert!(urls.is_empty());
+                assert!(mode.is_none());
+            }
+            other => panic!("expected Clean, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_clean_urls_and_mode() {
+        let cli = Cli::parse_from([
+            "clink",
+            "clean",
+            "--mode",
+            "your_mom",
+            "https://a.test/?fbclid=1",
+            "https://b.test/",
+        ]);
+        match cli.command {
+            Some(Command::Clean { urls, mode }) => {
+                assert_eq!(urls, vec!["https://a.test/?fbclid=1", "https://b.test/"]);
+                assert_eq!(mode, Some(Mode::YourMom));
+            }
+            other => panic!("expected Clean, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        let cli = Cli::parse_from(["clink", "explain", "--json", "https://a.test/"]);
+        match cli.command {
+            Some(Command::Explain { urls, json }) => {
+                assert_eq!(urls, vec!["https://a.test/"]);
+                assert!(json);
+            }
+            other => panic!("expected Explain, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["clink", "explain"]).is_err());
+    }
+
     #[test]
     fn test_parse_update() {
         let cli = Cli::parse_from(["clink", "update"]);
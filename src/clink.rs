@@ -1,25 +1,108 @@
-use crate::config::ClinkConfig;
+use crate::canonicalize::canonicalize;
+use crate::config::{ClinkConfig, CompleteProviderAction};
 use crate::mode::Mode;
-use crate::provider::{CompiledProvider, CompiledRules, check_provider};
+use crate::provider::{
+    CompiledProvider, GLOBAL_PROVIDER, ProviderConfig, RuleKind, RuleMatch, check_provider,
+};
+use crate::query::{self, QueryParam};
 use chrono::prelude::*;
-use linkify::{LinkFinder, LinkKind};
-use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use linkify::{Link, LinkFinder, LinkKind};
 use rand::RngExt;
+use serde::Serialize;
 use url::Url;
 
-const QUERY_COMPONENT_KEY: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'&').add(b'=').add(b'+');
-const QUERY_COMPONENT_VALUE: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'&').add(b'+');
-
+#[derive(Default, Serialize)]
 pub struct CleanResult {
     pub text: String,
     pub urls_cleaned: u32,
     pub params_removed: u32,
     pub exits_unwrapped: u32,
+    pub urls_canonicalized: u32,
+    pub warnings: Vec<String>,
+    // Each link that changed, as found and as rewritten.
+    #[serde(skip)]
+    pub rewritten: Vec<(String, String)>,
+}
+
+// Per-URL trace produced by `Clink::explain`. Mirrors the steps
+// `find_and_replace` takes so a surprising result can be attributed to the
+// provider and rule responsible.
+#[derive(Serialize, Debug)]
+pub struct LinkReport {
+    pub original: String,
+    pub cleaned: String,
+    pub redirects: Vec<RedirectHop>,
+    pub providers: Vec<ProviderReport>,
+    pub mode: Mode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode_from: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub raw_rules: Vec<RawRuleHit>,
+    pub params: Vec<ParamReport>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub canonicalized: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RedirectHop {
+    pub provider: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProviderReport {
+    pub provider: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub complete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_by: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RawRuleHit {
+    pub provider: String,
+    pub rule: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ParamReport {
+    pub key: String,
+    pub fragment: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracked_by: Option<RuleTrace>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unruled_by: Option<RuleTrace>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RuleTrace {
+    pub provider: String,
+    pub rule: String,
+    pub kind: RuleKind,
+}
+
+impl From<(&str, RuleMatch<'_>)> for RuleTrace {
+    fn from((provider, m): (&str, RuleMatch<'_>)) -> Self {
+        RuleTrace {
+            provider: provider.to_string(),
+            rule: m.rule.to_string(),
+            kind: m.kind,
+        }
+    }
+}
+
+// The mode in effect for one URL after provider overrides are applied.
+// `source` names the provider whose `mode` won, None for the top-level mode.
+struct ResolvedMode<'a> {
+    mode: Mode,
+    replace_to: &'a str,
+    source: Option<&'a str>,
 }
 
 pub struct Clink {
     config: ClinkConfig,
-    global_rules: CompiledRules,
+    global: CompiledProvider,
     scoped_providers: Vec<CompiledProvider>,
     finder: LinkFinder,
 }
@@ -32,17 +115,25 @@ impl Clink {
             }
         }
 
-        let global_rules = config
-            .providers
-            .get("global")
-            .map_or_else(|| CompiledRules::new(&[]), |p| CompiledRules::new(&p.rules));
+        let global = config.providers.get(GLOBAL_PROVIDER).map_or_else(
+            || CompiledProvider::global(&ProviderConfig::default()),
+            CompiledProvider::global,
+        );
 
-        let scoped_providers: Vec<CompiledProvider> = config
+        // `providers` is a HashMap, so fix the order here: the first provider
+        // that can unwrap a redirect wins, and that must not change between
+        // runs or reloads. Higher priority first, then by name.
+        let mut scoped_providers: Vec<CompiledProvider> = config
             .providers
             .iter()
-            .filter(|(name, _)| name.as_str() != "global")
-            .filter_map(|(_, cfg)| CompiledProvider::new(cfg))
+            .filter(|(name, _)| name.as_str() != GLOBAL_PROVIDER)
+            .filter_map(|(name, cfg)| CompiledProvider::new(name, cfg))
             .collect();
+        scoped_providers.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| a.name.cmp(&b.name))
+        });
 
         let mut finder = LinkFinder::new();
         finder.kinds(&[LinkKind::Url]);
@@ -53,16 +144,24 @@ impl Clink {
 
         Clink {
             config,
-            global_rules,
+            global,
             scoped_providers,
             finder,
         }
     }
 
+    // The links `find_and_replace` would look at, for callers that splice
+    // cleaned links into text of their own.
+    pub fn links<'t>(&self, input: &'t str) -> impl Iterator<Item = Link<'t>> {
+        self.finder.links(input)
+    }
+
     pub fn find_and_replace(&self, input: &str) -> CleanResult {
-        let mut urls_cleaned: u32 = 0;
         let mut params_removed: u32 = 0;
         let mut exits_unwrapped: u32 = 0;
+        let mut urls_canonicalized: u32 = 0;
+        let mut warnings = Vec::new();
+        let mut rewritten = Vec::new();
 
         // Splice each cleaned URL back at its linkify byte range rather than
         // search-and-replace on the whole string. Naive `String::replace`
@@ -74,7 +173,7 @@ impl Clink {
 
         for link in self.finder.links(input) {
             res.push_str(&input[last_end..link.start()]);
-            let Some((mut l, was_exit)) = self.parse_link(link.as_str()) else {
+            let Some((mut l, hops)) = self.parse_link(link.as_str()) else {
                 // linkify is more permissive than url::Url (e.g. accepts
                 // out-of-range ports). Keep the original text rather than
                 // crashing the daemon on user clipboard content.
@@ -82,43 +181,81 @@ impl Clink {
                 last_end = link.end();
                 continue;
             };
+            let was_exit = !hops.is_empty();
             if was_exit {
                 exits_unwrapped += 1;
             }
             let normalized_original = l.to_string();
-            #[allow(clippy::cast_possible_truncation)]
-            let original_param_count = l.query_pairs().count() as u32;
-
             let matching_providers = self.find_matching_providers(l.as_str());
+            let mode = self.resolve_mode(&matching_providers);
+            if let Some(complete) = matching_providers.iter().find(|p| p.complete) {
+                match self.config.complete_provider_action {
+                    CompleteProviderAction::Ignore => {}
+                    CompleteProviderAction::Warn => warnings.push(format!(
+                        "link to {} matches complete provider '{}' (ad or tracking domain)",
+                        l.host_str().unwrap_or("unknown host"),
+                        complete.name
+                    )),
+                    CompleteProviderAction::Placeholder if mode.mode == Mode::Off => {}
+                    CompleteProviderAction::Placeholder => {
+                        let placeholder = &self.config.complete_provider_placeholder;
+                        res.push_str(placeholder);
+                        rewritten.push((link.as_str().to_string(), placeholder.clone()));
+                        last_end = link.end();
+                        continue;
+                    }
+                }
+            }
+            if mode.mode != Mode::Off {
+                if let Some((stripped, _)) = strip_raw_rules(&l, &matching_providers) {
+                    l = stripped;
+                }
+            }
+            let params = query::parse(l.query().unwrap_or_default());
+            #[allow(clippy::cast_possible_truncation)]
+            let original_param_count = params.len() as u32;
 
-            let query = self.process_query(
-                l.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())),
-                &matching_providers,
-            );
+            let query = process_query(params.iter().cloned(), &matching_providers, &mode);
             #[allow(clippy::cast_possible_truncation)]
             let new_param_count = query.len() as u32;
-            let removed = original_param_count.saturating_sub(new_param_count);
-            l.set_query(None);
-            if !query.is_empty() {
-                let qs = query
-                    .iter()
-                    .map(|(k, v)| {
-                        format!(
-                            "{}={}",
-                            utf8_percent_encode(k, QUERY_COMPONENT_KEY),
-                            utf8_percent_encode(v, QUERY_COMPONENT_VALUE),
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("&");
-                l.set_query(Some(&qs));
+            let mut removed = original_param_count.saturating_sub(new_param_count);
+            // Untouched queries keep every byte, empty segments included.
+            if query != params {
+                l.set_query(None);
+                if !query.is_empty() {
+                    l.set_query(Some(&query::join(&query)));
+                }
+            }
+            let cleaned_fragment = l
+                .fragment()
+                .and_then(|f| clean_fragment(f, &matching_providers, &mode));
+            if let Some((fragment, fragment_removed)) = cleaned_fragment {
+                removed += fragment_removed;
+                l.set_fragment(fragment.as_deref());
+            }
+            let query_changed = l.as_str() != normalized_original;
+            // Canonicalization runs on the cleaned URL, but a link that only
+            // needed canonicalizing still counts as cleaned. `off` means the
+            // link is left exactly as copied.
+            let canonicalized = match &self.config.canonicalize {
+                // After an unwrap there is no copied text for the target.
+                Some(config) if mode.mode != Mode::Off => {
+                    let original = if was_exit {
+                        normalized_original.as_str()
+                    } else {
+                        link.as_str()
+                    };
+                    canonicalize(original, &mut l, config)
+                }
+                _ => false,
+            };
+            if canonicalized {
+                urls_canonicalized += 1;
             }
-            let new_url = l.as_str();
-            let query_changed = new_url != normalized_original;
-            if query_changed || was_exit {
-                urls_cleaned += 1;
+            if query_changed || was_exit || canonicalized {
                 params_removed += removed;
-                res.push_str(new_url);
+                res.push_str(l.as_str());
+                rewritten.push((link.as_str().to_string(), l.to_string()));
             } else {
                 res.push_str(link.as_str());
             }
@@ -128,101 +265,131 @@ impl Clink {
 
         CleanResult {
             text: res,
-            urls_cleaned,
+            #[allow(clippy::cast_possible_truncation)]
+            urls_cleaned: rewritten.len() as u32,
             params_removed,
             exits_unwrapped,
+            urls_canonicalized,
+            warnings,
+            rewritten,
         }
     }
 
-    fn find_matching_providers(&self, url: &str) -> Vec<&CompiledProvider> {
-        self.scoped_providers
-            .iter()
-            .filter(|p| p.matches_url(url))
-            .collect()
-    }
-
-    fn process_query(
-        &self,
-        query: impl Iterator<Item = (String, String)>,
-        providers: &[&CompiledProvider],
-    ) -> Vec<(String, String)> {
-        match self.config.mode {
-            Mode::Remove => self.filter(query, providers),
-            Mode::Replace => self.replace(query, providers),
-            Mode::YourMom => {
-                let date = Utc::now();
-                if date.month() == 5 && date.day() == 9 {
-                    self.filter(query, providers)
-                } else {
-                    let mut tmp = self.filter(query, providers);
-                    tmp.push(("utm_source".to_string(), "your_mom".to_string()));
-                    tmp
-                }
-            }
-            Mode::Evil => {
-                let mut rng = rand::rng();
-                query
-                    .map(|(key, value)| {
-                        if self.is_tracked(&key, providers) {
-                            // char count, not byte length — multibyte values
-                            // would otherwise yield out-of-range indices into
-                            // the char vector; 0/1-char values can't swap.
-                            let char_count = value.chars().count();
-                            if char_count < 2 {
-                                (key, value)
-                            } else {
-                                let a = rng.random_range(0..char_count);
-                                let b = rng.random_range(0..char_count);
-                                (key, swap_two_chars(&value, a, b))
-                            }
-                        } else {
-                            (key, value)
-                        }
+    pub fn explain(&self, input: &str) -> Vec<LinkReport> {
+        self.finder
+            .links(input)
+            .filter_map(|link| {
+                let (mut l, redirects) = self.parse_link(link.as_str())?;
+                // The global provider is only listed when an exception shields
+                // the URL from it; otherwise it applies implicitly.
+                let providers = std::iter::once(&self.global)
+                    .filter(|g| g.matching_exception(l.as_str()).is_some())
+                    .chain(self.scoped_providers.iter())
+                    .filter(|p| p.matches_url_pattern(l.as_str()))
+                    .map(|p| ProviderReport {
+                        provider: p.name.clone(),
+                        complete: p.complete,
+                        excluded_by: p.matching_exception(l.as_str()).map(str::to_string),
                     })
-                    .collect()
-            }
-        }
-    }
-
-    fn is_tracked(&self, key: &str, providers: &[&CompiledProvider]) -> bool {
-        self.global_rules.is_tracked(key) || providers.iter().any(|p| p.rules.is_tracked(key))
-    }
-
-    fn filter(
-        &self,
-        query: impl Iterator<Item = (String, String)>,
-        providers: &[&CompiledProvider],
-    ) -> Vec<(String, String)> {
-        query
-            .filter(|(key, _)| !self.is_tracked(key, providers))
+                    .collect();
+                let matching_providers = self.find_matching_providers(l.as_str());
+                let mode = self.resolve_mode(&matching_providers);
+                // Rules only show up when `find_and_replace` applies them:
+                // not under `off`, nor to a link the placeholder replaces.
+                let applied = mode.mode != Mode::Off
+                    && !(self.config.complete_provider_action
+                        == CompleteProviderAction::Placeholder
+                        && matching_providers.iter().any(|p| p.complete));
+                let rules: &[&CompiledProvider] = if applied { &matching_providers } else { &[] };
+                let raw_rules = match strip_raw_rules(&l, rules) {
+                    Some((stripped, hits)) => {
+                        l = stripped;
+                        hits
+                    }
+                    _ => Vec::new(),
+                };
+                // Parsed the way `find_and_replace` parses them, so the trace
+                // lists exactly the params the cleaner looks at.
+                let fragment_params = l
+                    .fragment()
+                    .and_then(split_fragment_query)
+                    .map(|(_, query)| query::parse(query))
+                    .unwrap_or_default();
+                let params = query::parse(l.query().unwrap_or_default())
+                    .into_iter()
+                    .map(|param| (param, false))
+                    .chain(fragment_params.into_iter().map(|param| (param, true)))
+                    .map(|(param, fragment)| ParamReport {
+                        tracked_by: tracking_rule(&param.key, rules).map(RuleTrace::from),
+                        unruled_by: unrule(&param.key, rules).map(RuleTrace::from),
+                        key: param.key,
+                        fragment,
+                    })
+                    .collect();
+                let result = self.find_and_replace(link.as_str());
+                Some(LinkReport {
+                    original: link.as_str().to_string(),
+                    cleaned: result.text,
+                    redirects,
+                    providers,
+                    mode: mode.mode,
+                    mode_from: mode.source.map(str::to_string),
+                    raw_rules,
+                    params,
+                    canonicalized: result.urls_canonicalized > 0,
+                })
+            })
             .collect()
     }
 
-    fn replace(
-        &self,
-        query: impl Iterator<Item = (String, String)>,
-        providers: &[&CompiledProvider],
-    ) -> Vec<(String, String)> {
-        query
-            .map(|(key, value)| {
-                if self.is_tracked(&key, providers) {
-                    (key, self.config.replace_to.clone())
-                } else {
-                    (key, value)
-                }
-            })
+    // Global first (unless one of its exceptions shields the URL), then the
+    // scoped providers in priority order.
+    fn find_matching_providers(&self, url: &str) -> Vec<&CompiledProvider> {
+        std::iter::once(&self.global)
+            .chain(self.scoped_providers.iter())
+            .filter(|p| p.matches_url(url))
             .collect()
     }
 
-    fn try_unwrap_redirect(&self, url: &str) -> (String, bool) {
-        for provider in &self.scoped_providers {
-            if provider.matches_url(url) {
-                if let Some(dest) = provider.try_redirect(url) {
-                    return (dest, true);
-                }
-            }
+    // Providers that set `mode` override the top-level one. When several
+    // matching providers disagree, the least destructive mode wins (off, then
+    // replace, evil, your_mom, remove), so a partner or analytics override is
+    // never silently undone by a broader provider on the same URL. Remaining
+    // ties go to the provider that comes first in priority order (see
+    // `Clink::new`). `replace_to` comes from the winning provider, else from
+    // the first matching provider that sets it, else top level.
+    fn resolve_mode<'a>(&'a self, providers: &[&'a CompiledProvider]) -> ResolvedMode<'a> {
+        let winner = providers
+            .iter()
+            .filter_map(|p| p.mode.map(|m| (p, m)))
+            .min_by_key(|(_, m)| preservation_rank(*m));
+        let fallback_replace_to = providers
+            .iter()
+            .find_map(|p| p.replace_to.as_deref())
+            .unwrap_or(&self.config.replace_to);
+
+        match winner {
+            Some((p, mode)) => ResolvedMode {
+                mode,
+                replace_to: p.replace_to.as_deref().unwrap_or(fallback_replace_to),
+                source: Some(p.name.as_str()),
+            },
+            None => ResolvedMode {
+                mode: self.config.mode,
+                replace_to: fallback_replace_to,
+                source: None,
+            },
         }
-        (url.to_string(), false)
+    }
+
+    // Scoped providers get the first chance to unwrap; global redirections
+    // are the catch-all for redirectors no scoped provider knows.
+    fn find_redirect(&self, url: &str) -> Option<(&CompiledProvider, String)> {
+        self.scoped_providers
+            .iter()
+            .chain(std::iter::once(&self.global))
+            .filter(|p| p.matches_url(url))
+            .find_map(|p| p.try_redirect(url).map(|dest| (p, dest)))
     }
 
     // Redirect captures (e.g. exit.sc's `url=...` value) aren't guaranteed to
@@ -237,29 +404,245 @@ impl Clink {
     // Returns None when the original link itself isn't url::Url-parseable —
     // linkify is more permissive than url::Url, so the caller must fall back
     // to the raw clipboard text rather than panicking.
-    fn parse_link(&self, link: &str) -> Option<(Url, bool)> {
+    //
+    // The returned hops are the successful unwraps in order; an empty list
+    // means the link was not a redirect.
+    //
+    // A redirector whose providers resolve to `mode = "off"` is left
+    // wrapped, like any other link under `off`.
+    fn parse_link(&self, link: &str) -> Option<(Url, Vec<RedirectHop>)> {
         const MAX_UNWRAPS: u32 = 5;
         let mut current = link.to_string();
-        let mut any_unwrap = false;
+        let mut hops = Vec::new();
         for _ in 0..MAX_UNWRAPS {
-            let (unwrapped, was_exit) = self.try_unwrap_redirect(&current);
-            if !was_exit {
+            if self
+                .resolve_mode(&self.find_matching_providers(&current))
+                .mode
+                == Mode::Off
+            {
                 break;
             }
+            let Some((provider, unwrapped)) = self.find_redirect(&current) else {
+                break;
+            };
             if Url::parse(&unwrapped).is_err() {
                 // Garbage at this step — keep what we already unwrapped.
                 break;
             }
-            current = unwrapped;
-            any_unwrap = true;
+            hops.push(RedirectHop {
+                provider: provider.name.clone(),
+                from: std::mem::replace(&mut current, unwrapped.clone()),
+                to: unwrapped,
+            });
         }
-        if any_unwrap {
+        if !hops.is_empty() {
             return Some((
                 Url::parse(&current).expect("validated parseable above"),
-                true,
+                hops,
             ));
         }
-        Url::parse(link).ok().map(|u| (u, false))
+        Url::parse(link).ok().map(|u| (u, hops))
+    }
+}
+
+// SPAs and newsletters put tracking keys in the fragment in two shapes: a
+// client-side route with its own query (`#/page?utm_source=x`, returned as
+// `(Some("/page"), "utm_source=x")`) or a fragment that is itself a query
+// (`#utm_medium=y&section=2`, returned as `(None, ...)`). A plain anchor
+// like `#section` has neither `?` nor `=` and yields None.
+fn split_fragment_query(fragment: &str) -> Option<(Option<&str>, &str)> {
+    match fragment.split_once('?') {
+        Some((route, query)) => Some((Some(route), query)),
+        None if fragment.contains('=') => Some((None, fragment)),
+        None => None,
+    }
+}
+
+fn process_query(
+    query: impl Iterator<Item = QueryParam>,
+    providers: &[&CompiledProvider],
+    mode: &ResolvedMode,
+) -> Vec<QueryParam> {
+    match mode.mode {
+        Mode::Off => query.collect(),
+        Mode::Remove => filter(query, providers),
+        Mode::Replace => replace(query, providers, mode.replace_to),
+        Mode::YourMom => {
+            let date = Utc::now();
+            if date.month() == 5 && date.day() == 9 {
+                filter(query, providers)
+            } else {
+                let mut tmp = filter(query, providers);
+                tmp.push(QueryParam::new("utm_source", "your_mom"));
+                tmp
+            }
+        }
+        Mode::Evil => {
+            let mut rng = rand::rng();
+            query
+                .map(|mut param| {
+                    if is_tracked(&param.key, providers) {
+                        // char count, not byte length — multibyte values
+                        // would otherwise yield out-of-range indices into
+                        // the char vector; 0/1-char values can't swap.
+                        let char_count = param.value.chars().count();
+                        if char_count >= 2 {
+                            let a = rng.random_range(0..char_count);
+                            let b = rng.random_range(0..char_count);
+                            let swapped = swap_two_chars(&param.value, a, b);
+                            param.set_value(swapped);
+                        }
+                    }
+                    param
+                })
+                .collect()
+        }
+    }
+}
+
+// Applies the same rules and mode to a query-like fragment. Returns None
+// when the fragment is a plain anchor or nothing in it is tracked, so the
+// original fragment bytes survive untouched. Otherwise returns the new
+// fragment (None drops the `#` entirely) and how many params went away.
+fn clean_fragment(
+    fragment: &str,
+    providers: &[&CompiledProvider],
+    mode: &ResolvedMode,
+) -> Option<(Option<String>, u32)> {
+    if mode.mode == Mode::Off {
+        return None;
+    }
+    let (route, query) = split_fragment_query(fragment)?;
+    let pairs = query::parse(query);
+    if !pairs.iter().any(|p| is_tracked(&p.key, providers)) {
+        return None;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let original_count = pairs.len() as u32;
+    // your_mom's extra utm_source belongs in the query, not duplicated
+    // into every fragment.
+    let pairs = match mode.mode {
+        Mode::YourMom => filter(pairs.into_iter(), providers),
+        _ => process_query(pairs.into_iter(), providers, mode),
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let removed = original_count.saturating_sub(pairs.len() as u32);
+    let query = query::join(&pairs);
+    let fragment = match route {
+        Some(route) if query.is_empty() && route.is_empty() => None,
+        Some(route) if query.is_empty() => Some(route.to_string()),
+        Some(route) => Some(format!("{route}?{query}")),
+        None if query.is_empty() => None,
+        None => Some(query),
+    };
+    Some((fragment, removed))
+}
+
+fn filter(
+    query: impl Iterator<Item = QueryParam>,
+    providers: &[&CompiledProvider],
+) -> Vec<QueryParam> {
+    query.filter(|p| !is_tracked(&p.key, providers)).collect()
+}
+
+fn replace(
+    query: impl Iterator<Item = QueryParam>,
+    providers: &[&CompiledProvider],
+    replace_to: &str,
+) -> Vec<QueryParam> {
+    query
+        .map(|mut param| {
+            if is_tracked(&param.key, providers) {
+                param.set_value(replace_to.to_string());
+            }
+            param
+        })
+        .collect()
+}
+
+fn is_tracked(key: &str, providers: &[&CompiledProvider]) -> bool {
+    if unrule(key, providers).is_some() {
+        return false;
+    }
+    if let Some(kept) = allowlist_keeps(key, providers) {
+        return !kept;
+    }
+    providers.iter().any(|p| p.rules.is_tracked(key))
+}
+
+// Providers are consulted in match order (global first); the first rule
+// that claims the key is the one reported by `explain`. A
+// matching provider with a `keep` list overrides both: the allowlist
+// alone decides. Unruled keys are never tracked.
+fn tracking_rule<'a>(
+    key: &str,
+    providers: &[&'a CompiledProvider],
+) -> Option<(&'a str, RuleMatch<'a>)> {
+    if unrule(key, providers).is_some() {
+        return None;
+    }
+    if let Some(kept) = allowlist_keeps(key, providers) {
+        if kept {
+            return None;
+        }
+        let provider = providers.iter().find(|p| p.keep.is_some())?;
+        return Some((
+            provider.name.as_str(),
+            RuleMatch {
+                rule: "keep",
+                kind: RuleKind::Allowlist,
+            },
+        ));
+    }
+    providers
+        .iter()
+        .find_map(|p| p.rules.matching_rule(key).map(|m| (p.name.as_str(), m)))
+}
+
+// Raw rules run on the whole URL before any query processing, in the
+// order of `find_matching_providers`. Returns None when nothing fired
+// or the stripped string no longer parses, so the caller keeps the URL.
+fn strip_raw_rules(url: &Url, providers: &[&CompiledProvider]) -> Option<(Url, Vec<RawRuleHit>)> {
+    let mut current = url.as_str().to_string();
+    let mut hits = Vec::new();
+    for provider in providers {
+        let (stripped, fired) = provider.raw_rules.strip(&current);
+        current = stripped;
+        hits.extend(fired.into_iter().map(|rule| RawRuleHit {
+            provider: provider.name.clone(),
+            rule: rule.to_string(),
+        }));
+    }
+    if hits.is_empty() {
+        return None;
+    }
+    Url::parse(&current).ok().map(|u| (u, hits))
+}
+
+// Unrules win over every rule and allowlist: they exist so a local config
+// can switch off a remote or built-in rule it can't edit.
+fn unrule<'a>(key: &str, providers: &[&'a CompiledProvider]) -> Option<(&'a str, RuleMatch<'a>)> {
+    providers
+        .iter()
+        .find_map(|p| p.unrules.matching_rule(key).map(|m| (p.name.as_str(), m)))
+}
+
+// None when no matching provider has a `keep` list. Otherwise whether any of
+// those lists keeps the key: when several allowlists match, their union
+// survives, in line with the least-destructive mode rule.
+fn allowlist_keeps(key: &str, providers: &[&CompiledProvider]) -> Option<bool> {
+    let mut lists = providers.iter().filter_map(|p| p.keep.as_ref()).peekable();
+    lists.peek()?;
+    Some(lists.any(|keep| keep.is_tracked(key)))
+}
+
+fn preservation_rank(mode: Mode) -> u8 {
+    match mode {
+        Mode::Off => 0,
+        Mode::Replace => 1,
+        Mode::Evil => 2,
+        Mode::YourMom => 3,
+        Mode::Remove => 4,
     }
 }
 
@@ -426,8 +809,8 @@ mod find_and_replace {
             replace_to: "clink".to_string(),
             sleep_duration: 150,
             providers,
-            verbose: false,
             remote: None,
+            ..ClinkConfig::default()
         });
         assert_eq!(
             clink
@@ -461,8 +844,8 @@ mod find_and_replace {
             replace_to: "clink".to_string(),
             sleep_duration: 150,
             providers,
-            verbose: false,
             remote: None,
+            ..ClinkConfig::default()
         });
         assert_eq!(
             clink
@@ -483,6 +866,7 @@ mod find_and_replace {
                 rules: vec!["ref".into()],
                 redirections: vec![],
                 exceptions: vec![r"^https?://shop\.example/admin".into()],
+                ..Default::default()
             },
         );
         let clink = Clink::new(ClinkConfig {
@@ -490,8 +874,8 @@ mod find_and_replace {
             replace_to: "clink".to_string(),
             sleep_duration: 150,
             providers,
-            verbose: false,
             remote: None,
+            ..ClinkConfig::default()
         });
         assert_eq!(
             clink
@@ -519,6 +903,7 @@ mod find_and_replace {
                 rules: vec![],
                 redirections: vec![r"url=([^&]+)".into()],
                 exceptions: vec![r"^https?://exit\.sc/admin".into()],
+                ..Default::default()
             },
         );
         let clink = Clink::new(ClinkConfig {
@@ -526,8 +911,8 @@ mod find_and_replace {
             replace_to: "clink".to_string(),
             sleep_duration: 150,
             providers,
-            verbose: false,
             remote: None,
+            ..ClinkConfig::default()
         });
         let excepted = "https://exit.sc/admin?url=https%3A%2F%2Fexample.com";
         let result = clink.find_and_replace(excepted);
@@ -786,192 +1171,815 @@ mod find_and_replace {
     }
 
     #[test]
-    fn clean_result_no_changes() {
-        let clink = Clink::new(test_config(Mode::Remove));
-        let result = clink.find_and_replace("https://test.test/?keep=yes");
-        assert_eq!(result.urls_cleaned, 0);
-        assert_eq!(result.params_removed, 0);
-        assert_eq!(result.exits_unwrapped, 0);
-    }
-
-    #[test]
-    fn normalization_only_not_counted_as_cleaned() {
-        let clink = Clink::new(test_config(Mode::Remove));
-        let result = clink.find_and_replace("https://example.com");
-        assert_eq!(result.urls_cleaned, 0);
-        assert_eq!(result.params_removed, 0);
-        assert_eq!(result.text, "https://example.com");
-    }
-
-    #[test]
-    fn clean_result_multiple_urls() {
+    fn fragment_route_query_is_cleaned() {
         let clink = Clink::new(test_config(Mode::Remove));
-        let result = clink.find_and_replace(
-            "https://test.test/?fbclid=a\nhttps://test.test/?utm_source=b&utm_medium=c",
-        );
-        assert_eq!(result.urls_cleaned, 2);
-        assert_eq!(result.params_removed, 3);
+        let result = clink.find_and_replace("https://app.test/#/page?utm_source=x&id=1");
+        assert_eq!(result.text, "https://app.test/#/page?id=1");
+        assert_eq!(result.urls_cleaned, 1);
+        assert_eq!(result.params_removed, 1);
     }
 
     #[test]
-    fn twitter_srcset_param_not_stripped_by_unanchored_src_rule() {
-        // Twitter's bundled ClearURLs rule `(?:ref_?)?src` must match
-        // anchored. Without anchoring, `?srcset=…` would be stripped because
-        // "src" is a substring of "srcset".
+    fn fragment_query_is_cleaned() {
         let clink = Clink::new(test_config(Mode::Remove));
         assert_eq!(
             clink
-                .find_and_replace("https://twitter.com/user/status/1?srcset=app&keep=yes")
+                .find_and_replace("https://news.test/post?id=3#utm_medium=y&section=2")
                 .text,
-            "https://twitter.com/user/status/1?srcset=app&keep=yes",
-            "srcset param must not be stripped by anchored `src` rule"
+            "https://news.test/post?id=3#section=2"
         );
     }
 
     #[test]
-    fn twitter_src_param_stripped() {
+    fn fragment_emptied_by_cleaning_is_dropped() {
         let clink = Clink::new(test_config(Mode::Remove));
         assert_eq!(
             clink
-                .find_and_replace("https://twitter.com/user/status/1?src=app&keep=yes")
+                .find_and_replace("https://news.test/post#utm_source=x&fbclid=y")
                 .text,
-            "https://twitter.com/user/status/1?keep=yes"
+            "https://news.test/post"
         );
-    }
-
-    #[test]
-    fn fbclid_stripped_case_insensitively() {
-        let clink = Clink::new(test_config(Mode::Remove));
         assert_eq!(
             clink
-                .find_and_replace("https://test.test/?Fbclid=abc&keep=yes")
+                .find_and_replace("https://app.test/#/inbox?fbclid=y")
                 .text,
-            "https://test.test/?keep=yes"
+            "https://app.test/#/inbox"
         );
     }
 
-    // linkify is more permissive than url::Url — e.g. it accepts
-    // `https://example.com:65536/foo` (port out of u16 range) but url::Url
-    // rejects it. The daemon used to panic on these, then restart and re-read
-    // the same clipboard, looping. Skip the link instead.
     #[test]
-    fn unparseable_link_leaves_clipboard_untouched_no_panic() {
+    fn plain_anchor_fragment_is_untouched() {
         let clink = Clink::new(test_config(Mode::Remove));
-        let input = "before https://example.com:65536/foo?fbclid=abc after";
-        let result = clink.find_and_replace(input);
-        assert_eq!(
-            result.text, input,
-            "unparseable URL must leave the original text untouched"
-        );
+        let result = clink.find_and_replace("https://docs.test/guide#utm_source");
+        assert_eq!(result.text, "https://docs.test/guide#utm_source");
         assert_eq!(result.urls_cleaned, 0);
-        assert_eq!(result.params_removed, 0);
+
+        let result = clink.find_and_replace("https://docs.test/guide?fbclid=a#install");
+        assert_eq!(result.text, "https://docs.test/guide#install");
     }
 
     #[test]
-    fn unparseable_link_does_not_block_neighbour_url() {
+    fn fragment_without_tracked_keys_keeps_original_bytes() {
         let clink = Clink::new(test_config(Mode::Remove));
-        let input = "https://example.com:65536/foo https://test.test/?fbclid=abc";
+        let input = "https://app.test/#/search?q=a+b&tag=%7Ex";
         let result = clink.find_and_replace(input);
-        assert_eq!(
-            result.text, "https://example.com:65536/foo https://test.test/",
-            "second URL should still be cleaned even if the first is unparseable"
-        );
-        assert_eq!(result.urls_cleaned, 1);
+        assert_eq!(result.text, input);
+        assert_eq!(result.urls_cleaned, 0);
     }
 
     #[test]
-    fn lookalike_domains_do_not_match_shipped_providers() {
-        // Shipped patterns in default_config.toml must require a host-end
-        // boundary; otherwise `amazon.com.attacker.com/?sp_csd=...` would
-        // get its tracking param stripped, mangling the URL the user pasted.
-        let clink = Clink::new(test_config(Mode::Remove));
-
-        let amazon_lookalike = "https://amazon.com.attacker.com/?sp_csd=secret&keep=me";
-        let amazon_result = clink.find_and_replace(amazon_lookalike);
+    fn fragment_respects_mode() {
+        let clink = Clink::new(test_config(Mode::Replace));
         assert_eq!(
-            amazon_result.params_removed, 0,
-            "amazon's url_pattern must not match lookalike host"
+            clink
+                .find_and_replace("https://app.test/#/page?utm_source=x&id=1")
+                .text,
+            "https://app.test/#/page?utm_source=clink&id=1"
         );
 
-        let exit_lookalike = "https://exit.sc.attacker.com/?url=https%3A%2F%2Fexample.com";
-        let exit_result = clink.find_and_replace(exit_lookalike);
-        assert_eq!(
-            exit_result.exits_unwrapped, 0,
-            "exit.sc's url_pattern must not match lookalike host"
-        );
+        let clink = Clink::new(test_config(Mode::YourMom));
+        let text = clink
+            .find_and_replace("https://app.test/?a=1#/page?utm_source=x&id=1")
+            .text;
+        if Utc::now().month() == 5 && Utc::now().day() == 9 {
+            assert_eq!(text, "https://app.test/?a=1#/page?id=1");
+        } else {
+            assert_eq!(
+                text, "https://app.test/?a=1&utm_source=your_mom#/page?id=1",
+                "your_mom must only add its param to the query, not the fragment"
+            );
+        }
     }
 
     #[test]
-    fn clink_extras_survive_cache_without_them() {
-        // After `clink update` the cache contains only the translated remote
-        // (e.g. ClearURLs), which doesn't have niche redirectors like exit.sc
-        // or clink-curated extras like the explicit Amazon rules.
-        // Those entries live in the user's config template, so they must
-        // still apply even when the cache replaces the builtin layer.
-        let id = std::thread::current().id();
-        let cfg_path = std::env::temp_dir().join(format!("clink_test_extras_cfg_{id:?}.toml"));
-        let cache_dir = std::env::temp_dir().join(format!("clink_test_extras_cache_{id:?}"));
-        let _ = std::fs::remove_dir_all(&cache_dir);
-        std::fs::create_dir_all(&cache_dir).unwrap();
-
-        std::fs::write(&cfg_path, crate::config::DEFAULT_CONFIG_TEMPLATE).unwrap();
-
-        let cache_toml = "[providers.global]\nrules = [\"fbclid\", \"gclid\"]\n";
-        std::fs::write(cache_dir.join("remote_patterns.toml"), cache_toml).unwrap();
-
-        let mut cfg = crate::config::load_config(&cfg_path).unwrap();
-        crate::remote::resolve_patterns(&mut cfg, &cache_dir);
-        let clink = Clink::new(cfg);
-
+    fn raw_rules_apply_case_insensitively_to_whole_url() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "shop".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://shop\.test".into()),
+                raw_rules: vec!["/track/[0-9]+".into()],
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        });
         assert_eq!(
             clink
-                .find_and_replace("https://exit.sc/?url=https%3A%2F%2Fexample.com")
+                .find_and_replace("https://shop.test/TRACK/1/item/track/22?id=1")
                 .text,
-            "https://example.com/",
-            "exit.sc redirect must still unwrap when cache lacks it"
+            "https://shop.test/item?id=1"
         );
         assert_eq!(
             clink
-                .find_and_replace("https://www.amazon.com/dp/X?sp_csd=abc&keep=me")
+                .find_and_replace("https://other.test/track/1/item")
                 .text,
-            "https://www.amazon.com/dp/X?keep=me",
-            "amazon sp_csd must still strip when cache lacks it"
+            "https://other.test/track/1/item",
+            "raw rules are scoped to the provider's url_pattern"
         );
-
-        let _ = std::fs::remove_file(&cfg_path);
-        let _ = std::fs::remove_dir_all(&cache_dir);
     }
-}
-
-#[cfg(test)]
-mod unwrap_exit_params {
-    use crate::{clink::Clink, mode::Mode};
 
     #[test]
-    fn has_exit_url() {
-        let clink = Clink::new(super::test_config(Mode::Remove));
-        assert_eq!(
-            clink.try_unwrap_redirect(
-                "https://exit.sc/?url=https%3A%2F%2Fopen.spotify.com%2Fartist%2F3tEV3J5gW5BDMrJqE3NaBy%3Fsi%3D1mLk6MZSRGuol8rgwCe_Cg"
-            ).0,
-            "https://open.spotify.com/artist/3tEV3J5gW5BDMrJqE3NaBy?si=1mLk6MZSRGuol8rgwCe_Cg"
+    fn raw_rule_breaking_the_url_is_ignored() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            crate::provider::ProviderConfig {
+                raw_rules: vec!["^https://".into()],
+                rules: vec!["fbclid".into()],
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        });
+        assert_eq!(
+            clink.find_and_replace("https://test.test/?fbclid=a").text,
+            "https://test.test/"
+        );
+    }
+
+    fn override_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            crate::provider::ProviderConfig {
+                rules: vec!["utm_source".into(), "fbclid".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "analytics".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://stats\.corp".into()),
+                mode: Some(Mode::Replace),
+                replace_to: Some("redacted".into()),
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "partner".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://partner\.test".into()),
+                mode: Some(Mode::Off),
+                raw_rules: vec!["/ref=[^/?]*".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "partner_strict".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://partner\.test/shop".into()),
+                mode: Some(Mode::Remove),
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn provider_mode_overrides_top_level_mode() {
+        let clink = override_clink();
+        assert_eq!(
+            clink
+                .find_and_replace(
+                    "https://stats.corp/?utm_source=mail&id=1 https://other.test/?utm_source=x"
+                )
+                .text,
+            "https://stats.corp/?utm_source=redacted&id=1 https://other.test/"
+        );
+    }
+
+    #[test]
+    fn provider_mode_off_leaves_url_untouched() {
+        let clink = override_clink();
+        let input = "https://partner.test/item/ref=abc?utm_source=x#utm_source=y";
+        let result = clink.find_and_replace(input);
+        assert_eq!(result.text, input);
+        assert_eq!(result.urls_cleaned, 0);
+    }
+
+    #[test]
+    fn provider_mode_off_keeps_redirects_and_complete_links() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "partner".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://partner\.test".into()),
+                redirections: vec![r"^https?://partner\.test/out\?to=([^&]+)".into()],
+                complete_provider: Some(true),
+                mode: Some(Mode::Off),
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            complete_provider_action: CompleteProviderAction::Placeholder,
+            ..ClinkConfig::default()
+        });
+        for input in [
+            "https://partner.test/out?to=https%3A%2F%2Fa.test%2F%3Futm_source%3Dx",
+            "https://partner.test/banner?id=1",
+        ] {
+            let result = clink.find_and_replace(input);
+            assert_eq!(result.text, input);
+            assert_eq!(result.urls_cleaned, 0);
+            assert_eq!(result.exits_unwrapped, 0);
+        }
+    }
+
+    #[test]
+    fn least_destructive_provider_mode_wins() {
+        // partner (off) and partner_strict (remove) both match /shop.
+        let clink = override_clink();
+        let input = "https://partner.test/shop?utm_source=x";
+        assert_eq!(clink.find_and_replace(input).text, input);
+
+        let reports = clink.explain(input);
+        assert_eq!(reports[0].mode, Mode::Off);
+        assert_eq!(reports[0].mode_from.as_deref(), Some("partner"));
+        // Nothing was applied, so no rule is reported either.
+        assert_eq!(reports[0].params[0].key, "utm_source");
+        assert!(reports[0].params[0].tracked_by.is_none());
+    }
+
+    fn allowlist_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            crate::provider::ProviderConfig {
+                rules: vec!["t".into(), "utm_source".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "youtube".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://(?:www\.)?youtube\.com".into()),
+                redirections: vec![
+                    r"^https?://(?:www\.)?youtube\.com/redirect\?.*?q=([^&]+)".into(),
+                ],
+                keep: Some(vec!["v".into(), "t".into(), "list".into()]),
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "amazon".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://(?:www\.)?amazon\.com".into()),
+                keep: Some(vec![]),
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn keep_list_drops_everything_else() {
+        let clink = allowlist_clink();
+        let result = clink.find_and_replace(
+            "https://www.youtube.com/watch?v=abc&si=xyz&t=42&pp=new_tracker&list=PL1",
+        );
+        assert_eq!(
+            result.text, "https://www.youtube.com/watch?v=abc&t=42&list=PL1",
+            "t is kept even though a global rule tracks it"
+        );
+        assert_eq!(result.params_removed, 2);
+    }
+
+    #[test]
+    fn empty_keep_list_keeps_nothing() {
+        let clink = allowlist_clink();
+        assert_eq!(
+            clink
+                .find_and_replace("https://www.amazon.com/dp/X?psc=1&th=1")
+                .text,
+            "https://www.amazon.com/dp/X"
+        );
+    }
+
+    #[test]
+    fn keep_list_applies_after_redirect_unwrap() {
+        let clink = allowlist_clink();
+        // The redirector's own params aren't filtered away before its
+        // redirection captures `q`; the destination is then allowlisted.
+        assert_eq!(
+            clink
+                .find_and_replace(
+                    "https://www.youtube.com/redirect?event=x&q=https%3A%2F%2Fwww.amazon.com%2Fdp%2FX%3Fpsc%3D1"
+                )
+                .text,
+            "https://www.amazon.com/dp/X"
+        );
+        // Outside allowlisted providers the global rules still apply.
+        assert_eq!(
+            clink.find_and_replace("https://example.com/?t=1&id=2").text,
+            "https://example.com/?id=2"
+        );
+    }
+
+    #[test]
+    fn explain_reports_allowlist_drops() {
+        let clink = allowlist_clink();
+        let reports = clink.explain("https://www.youtube.com/watch?v=abc&si=xyz");
+        let params = &reports[0].params;
+        assert!(params[0].tracked_by.is_none());
+        let si = params[1].tracked_by.as_ref().unwrap();
+        assert_eq!(si.provider, "youtube");
+        assert_eq!(si.kind, RuleKind::Allowlist);
+    }
+
+    #[test]
+    fn provider_unrule_switches_off_builtin_rule() {
+        let mut cfg = test_config(Mode::Remove);
+        cfg.providers
+            .get_mut("amazon")
+            .unwrap()
+            .unrules
+            .push("t".into());
+        let clink = Clink::new(cfg);
+        assert_eq!(
+            clink
+                .find_and_replace("https://www.amazon.com/dp/X?t=30&psc=1")
+                .text,
+            "https://www.amazon.com/dp/X?t=30"
+        );
+    }
+
+    #[test]
+    fn global_regex_unrule_applies_everywhere() {
+        let mut cfg = test_config(Mode::Remove);
+        cfg.providers
+            .entry("global".to_string())
+            .or_default()
+            .unrules
+            .push("utm_(?:source|medium)".into());
+        let clink = Clink::new(cfg);
+        assert_eq!(
+            clink
+                .find_and_replace(
+                    "https://test.test/?utm_source=a&utm_medium=b&utm_campaign=c&fbclid=d"
+                )
+                .text,
+            "https://test.test/?utm_source=a&utm_medium=b"
+        );
+        let reports = clink.explain("https://test.test/?utm_source=a");
+        let unruled = reports[0].params[0].unruled_by.as_ref().unwrap();
+        assert_eq!(unruled.provider, "global");
+        assert_eq!(unruled.kind, RuleKind::Regex);
+        assert!(reports[0].params[0].tracked_by.is_none());
+    }
+
+    #[test]
+    fn unrule_beats_keep_list() {
+        let mut cfg = test_config(Mode::Remove);
+        let amazon = cfg.providers.get_mut("amazon").unwrap();
+        amazon.keep = Some(vec![]);
+        amazon.unrules.push("th".into());
+        let clink = Clink::new(cfg);
+        assert_eq!(
+            clink
+                .find_and_replace("https://www.amazon.com/dp/X?th=1&psc=1")
+                .text,
+            "https://www.amazon.com/dp/X?th=1"
+        );
+    }
+
+    fn competing_redirectors(priority: Option<i32>) -> Clink {
+        let mut providers = HashMap::new();
+        for (name, target) in [("alpha", "a"), ("beta", "b")] {
+            providers.insert(
+                name.to_string(),
+                crate::provider::ProviderConfig {
+                    url_pattern: Some(r"^https?://go\.test".into()),
+                    redirections: vec![format!(r"^https?://go\.test/\?.*?{target}=([^&]+)")],
+                    ..Default::default()
+                },
+            );
+        }
+        providers.get_mut("beta").unwrap().priority = priority;
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn equal_priority_redirectors_resolve_by_name() {
+        let url = "https://go.test/?b=https%3A%2F%2Fb.test%2F&a=https%3A%2F%2Fa.test%2F";
+        for _ in 0..5 {
+            let clink = competing_redirectors(None);
+            assert_eq!(clink.find_and_replace(url).text, "https://a.test/");
+        }
+    }
+
+    #[test]
+    fn higher_priority_redirector_wins() {
+        let url = "https://go.test/?b=https%3A%2F%2Fb.test%2F&a=https%3A%2F%2Fa.test%2F";
+        let clink = competing_redirectors(Some(200));
+        assert_eq!(clink.find_and_replace(url).text, "https://b.test/");
+    }
+
+    fn complete_provider_clink(action: CompleteProviderAction) -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "ads".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://ads\.test".into()),
+                complete_provider: Some(true),
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "global".to_string(),
+            crate::provider::ProviderConfig {
+                rules: vec!["fbclid".into()],
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            complete_provider_action: action,
+            complete_provider_placeholder: "[blocked]".into(),
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn complete_provider_warns_and_still_cleans() {
+        let clink = complete_provider_clink(CompleteProviderAction::Warn);
+        let result = clink.find_and_replace("see https://ads.test/c?fbclid=x&id=1");
+        assert_eq!(result.text, "see https://ads.test/c?id=1");
+        assert_eq!(result.warnings.len(), 1);
+        assert!(
+            result.warnings[0].contains("'ads'"),
+            "{:?}",
+            result.warnings
+        );
+        assert!(
+            result.warnings[0].contains("ads.test"),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn complete_provider_placeholder_replaces_link() {
+        let clink = complete_provider_clink(CompleteProviderAction::Placeholder);
+        let result = clink.find_and_replace("see https://ads.test/c?id=1 and https://ok.test/");
+        assert_eq!(result.text, "see [blocked] and https://ok.test/");
+        assert_eq!(result.urls_cleaned, 1);
+        assert!(result.warnings.is_empty());
+
+        let reports = clink.explain("https://ads.test/c?fbclid=x");
+        assert_eq!(reports[0].cleaned, "[blocked]");
+        assert!(reports[0].params[0].tracked_by.is_none());
+    }
+
+    #[test]
+    fn complete_provider_ignore_is_silent() {
+        let clink = complete_provider_clink(CompleteProviderAction::Ignore);
+        let result = clink.find_and_replace("https://ads.test/c?id=1");
+        assert_eq!(result.text, "https://ads.test/c?id=1");
+        assert_eq!(result.urls_cleaned, 0);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn complete_provider_redirect_is_unwrapped_first() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "ads".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://ads\.test".into()),
+                redirections: vec![r"^https?://ads\.test/click\?.*?adurl=([^&]+)".into()],
+                complete_provider: Some(true),
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            complete_provider_action: CompleteProviderAction::Placeholder,
+            ..ClinkConfig::default()
+        });
+        assert_eq!(
+            clink
+                .find_and_replace("https://ads.test/click?x=1&adurl=https%3A%2F%2Fshop.test%2F")
+                .text,
+            "https://shop.test/"
+        );
+    }
+
+    #[test]
+    fn clean_result_no_changes() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let result = clink.find_and_replace("https://test.test/?keep=yes");
+        assert_eq!(result.urls_cleaned, 0);
+        assert_eq!(result.params_removed, 0);
+        assert_eq!(result.exits_unwrapped, 0);
+    }
+
+    #[test]
+    fn normalization_only_not_counted_as_cleaned() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let result = clink.find_and_replace("https://example.com");
+        assert_eq!(result.urls_cleaned, 0);
+        assert_eq!(result.params_removed, 0);
+        assert_eq!(result.text, "https://example.com");
+    }
+
+    #[test]
+    fn clean_result_multiple_urls() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let result = clink.find_and_replace(
+            "https://test.test/?fbclid=a\nhttps://test.test/?utm_source=b&utm_medium=c",
+        );
+        assert_eq!(result.urls_cleaned, 2);
+        assert_eq!(result.params_removed, 3);
+    }
+
+    #[test]
+    fn twitter_srcset_param_not_stripped_by_unanchored_src_rule() {
+        // Twitter's bundled ClearURLs rule `(?:ref_?)?src` must match
+        // anchored. Without anchoring, `?srcset=…` would be stripped because
+        // "src" is a substring of "srcset".
+        let clink = Clink::new(test_config(Mode::Remove));
+        assert_eq!(
+            clink
+                .find_and_replace("https://twitter.com/user/status/1?srcset=app&keep=yes")
+                .text,
+            "https://twitter.com/user/status/1?srcset=app&keep=yes",
+            "srcset param must not be stripped by anchored `src` rule"
+        );
+    }
+
+    #[test]
+    fn twitter_src_param_stripped() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        assert_eq!(
+            clink
+                .find_and_replace("https://twitter.com/user/status/1?src=app&keep=yes")
+                .text,
+            "https://twitter.com/user/status/1?keep=yes"
+        );
+    }
+
+    #[test]
+    fn fbclid_stripped_case_insensitively() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        assert_eq!(
+            clink
+                .find_and_replace("https://test.test/?Fbclid=abc&keep=yes")
+                .text,
+            "https://test.test/?keep=yes"
+        );
+    }
+
+    // linkify is more permissive than url::Url — e.g. it accepts
+    // `https://example.com:65536/foo` (port out of u16 range) but url::Url
+    // rejects it. The daemon used to panic on these, then restart and re-read
+    // the same clipboard, looping. Skip the link instead.
+    #[test]
+    fn unparseable_link_leaves_clipboard_untouched_no_panic() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let input = "before https://example.com:65536/foo?fbclid=abc after";
+        let result = clink.find_and_replace(input);
+        assert_eq!(
+            result.text, input,
+            "unparseable URL must leave the original text untouched"
+        );
+        assert_eq!(result.urls_cleaned, 0);
+        assert_eq!(result.params_removed, 0);
+    }
+
+    #[test]
+    fn unparseable_link_does_not_block_neighbour_url() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let input = "https://example.com:65536/foo https://test.test/?fbclid=abc";
+        let result = clink.find_and_replace(input);
+        assert_eq!(
+            result.text, "https://example.com:65536/foo https://test.test/",
+            "second URL should still be cleaned even if the first is unparseable"
         );
+        assert_eq!(result.urls_cleaned, 1);
+    }
+
+    #[test]
+    fn lookalike_domains_do_not_match_shipped_providers() {
+        // Shipped patterns in default_config.toml must require a host-end
+        // boundary; otherwise `amazon.com.attacker.com/?sp_csd=...` would
+        // get its tracking param stripped, mangling the URL the user pasted.
+        let clink = Clink::new(test_config(Mode::Remove));
 
+        let amazon_lookalike = "https://amazon.com.attacker.com/?sp_csd=secret&keep=me";
+        let amazon_result = clink.find_and_replace(amazon_lookalike);
         assert_eq!(
-        clink.try_unwrap_redirect(
+            amazon_result.params_removed, 0,
+            "amazon's url_pattern must not match lookalike host"
+        );
+
+        let exit_lookalike = "https://exit.sc.attacker.com/?url=https%3A%2F%2Fexample.com";
+        let exit_result = clink.find_and_replace(exit_lookalike);
+        assert_eq!(
+            exit_result.exits_unwrapped, 0,
+            "exit.sc's url_pattern must not match lookalike host"
+        );
+    }
+
+    #[test]
+    fn clink_extras_survive_cache_without_them() {
+        // After `clink update` the cache contains only the translated remote
+        // (e.g. ClearURLs), which doesn't have niche redirectors like exit.sc
+        // or clink-curated extras like the explicit Amazon rules.
+        // Those entries live in the user's config template, so they must
+        // still apply even when the cache replaces the builtin layer.
+        let id = std::thread::current().id();
+        let cfg_path = std::env::temp_dir().join(format!("clink_test_extras_cfg_{id:?}.toml"));
+        let cache_dir = std::env::temp_dir().join(format!("clink_test_extras_cache_{id:?}"));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        std::fs::create_dir_all(&cache_dir).unwrap();
+
+        std::fs::write(&cfg_path, crate::config::DEFAULT_CONFIG_TEMPLATE).unwrap();
+
+        let cache_toml = "[providers.global]\nrules = [\"fbclid\", \"gclid\"]\n";
+        std::fs::write(cache_dir.join("remote_patterns.toml"), cache_toml).unwrap();
+
+        let mut cfg = crate::config::load_config(&cfg_path).unwrap();
+        crate::remote::resolve_patterns(&mut cfg, &cache_dir);
+        let clink = Clink::new(cfg);
+
+        assert_eq!(
+            clink
+                .find_and_replace("https://exit.sc/?url=https%3A%2F%2Fexample.com")
+                .text,
+            "https://example.com/",
+            "exit.sc redirect must still unwrap when cache lacks it"
+        );
+        assert_eq!(
+            clink
+                .find_and_replace("https://www.amazon.com/dp/X?sp_csd=abc&keep=me")
+                .text,
+            "https://www.amazon.com/dp/X?keep=me",
+            "amazon sp_csd must still strip when cache lacks it"
+        );
+
+        let _ = std::fs::remove_file(&cfg_path);
+        let _ = std::fs::remove_dir_all(&cache_dir);
+    }
+
+    #[test]
+    fn untouched_params_keep_their_encoding() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        assert_eq!(
+            clink
+                .find_and_replace(
+                    "https://example.com/search?q=a+b&path=%2Fdocs%2F&flag&fbclid=1&x=&to=/raw/"
+                )
+                .text,
+            "https://example.com/search?q=a+b&path=%2Fdocs%2F&flag&x=&to=/raw/"
+        );
+
+        let clink = Clink::new(test_config(Mode::Replace));
+        assert_eq!(
+            clink
+                .find_and_replace("https://example.com/?utm_source=x&q=a+b%20c")
+                .text,
+            "https://example.com/?utm_source=clink&q=a+b%20c"
+        );
+    }
+
+    #[test]
+    fn query_without_tracking_is_left_byte_for_byte() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let result = clink.find_and_replace("https://example.com/?a=1&&b=%7e");
+        assert_eq!(result.text, "https://example.com/?a=1&&b=%7e");
+        assert_eq!(result.urls_cleaned, 0);
+    }
+
+    fn canonical_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            ProviderConfig {
+                rules: vec!["fbclid".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "partner".to_string(),
+            ProviderConfig {
+                url_pattern: Some(r"^https?://partner\.test".into()),
+                mode: Some(Mode::Off),
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            canonicalize: Some(crate::canonicalize::Canonicalize::default()),
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn canonicalize_is_opt_in() {
+        let clink = Clink::new(ClinkConfig {
+            remote: None,
+            ..ClinkConfig::default()
+        });
+        let result = clink.find_and_replace("see https://Example.com:443/docs/index.html");
+        assert_eq!(result.text, "see https://Example.com:443/docs/index.html");
+        assert_eq!(result.urls_canonicalized, 0);
+    }
+
+    #[test]
+    fn canonicalize_counts_links_that_only_needed_it() {
+        let clink = canonical_clink();
+        let result = clink.find_and_replace(
+            "a https://Example.com:443/docs/index.html b https://example.com/x?fbclid=1&a=1&a=1 c https://example.com/ok",
+        );
+        assert_eq!(
+            result.text,
+            "a https://example.com/docs/ b https://example.com/x?a=1 c https://example.com/ok"
+        );
+        assert_eq!(result.urls_cleaned, 2);
+        assert_eq!(result.urls_canonicalized, 2);
+        assert_eq!(result.params_removed, 1);
+    }
+
+    #[test]
+    fn canonicalize_skips_links_in_off_mode() {
+        let clink = canonical_clink();
+        let result = clink.find_and_replace("https://partner.test/news/amp?a=1&a=1");
+        assert_eq!(result.text, "https://partner.test/news/amp?a=1&a=1");
+        assert_eq!(result.urls_cleaned, 0);
+    }
+
+    #[test]
+    fn canonicalize_applies_to_unwrapped_targets() {
+        let mut clink = canonical_clink();
+        clink.config.canonicalize = Some(crate::canonicalize::Canonicalize {
+            strip_amp: true,
+            ..Default::default()
+        });
+        clink.global = CompiledProvider::global(&ProviderConfig {
+            redirections: vec![r"[?&]to=([^&]+)".into()],
+            ..Default::default()
+        });
+        let result =
+            clink.find_and_replace("https://go.test/?to=https%3A%2F%2FNews.test%2Fstory%2Famp");
+        assert_eq!(result.text, "https://news.test/story");
+        assert_eq!(result.exits_unwrapped, 1);
+        assert_eq!(result.urls_canonicalized, 1);
+    }
+}
+
+#[cfg(test)]
+mod unwrap_exit_params {
+    use crate::{clink::Clink, mode::Mode};
+
+    #[test]
+    fn has_exit_url() {
+        let clink = Clink::new(super::test_config(Mode::Remove));
+        assert_eq!(
+            clink.parse_link(
+                "https://exit.sc/?url=https%3A%2F%2Fopen.spotify.com%2Fartist%2F3tEV3J5gW5BDMrJqE3NaBy%3Fsi%3D1mLk6MZSRGuol8rgwCe_Cg"
+            ).unwrap().0.to_string(),
+            "https://open.spotify.com/artist/3tEV3J5gW5BDMrJqE3NaBy?si=1mLk6MZSRGuol8rgwCe_Cg"
+        );
+
+        assert_eq!(
+        clink.parse_link(
             "https://www.google.com/url?sa=t&rct=j&q=&esrc=s&source=web&cd=&cad=rja&uact=8&ved=2ahUKEwjMuu2zrreBAxUt2gIHHaDVC_gQyCl6BAgqEAM&url=https%3A%2F%2Fwww.youtube.com%2Fwatch%3Fv%3DdQw4w9WgXcQ&usg=AOvVaw0aHtehaphMhOCAkCydRLZU&opi=89978449"
-        ).0,
+        ).unwrap().0.to_string(),
         "https://www.youtube.com/watch?v=dQw4w9WgXcQ"
-    )
+    );
     }
 
     #[test]
     fn has_no_exit_url() {
         let clink = Clink::new(super::test_config(Mode::Remove));
         assert_eq!(
-            clink.try_unwrap_redirect(
+            clink.parse_link(
                 "https://open.spotify.com/artist/3tEV3J5gW5BDMrJqE3NaBy?si=1mLk6MZSRGuol8rgwCe_Cg"
-            ).0,
+            ).unwrap().0.to_string(),
             "https://open.spotify.com/artist/3tEV3J5gW5BDMrJqE3NaBy?si=1mLk6MZSRGuol8rgwCe_Cg"
         );
     }
@@ -981,9 +1989,11 @@ mod unwrap_exit_params {
         let clink = Clink::new(super::test_config(Mode::Remove));
         assert_eq!(
             clink
-                .try_unwrap_redirect("https://www.google.it/url?url=https%3A%2F%2Fexample.com&sa=t")
-                .0,
-            "https://example.com"
+                .parse_link("https://www.google.it/url?url=https%3A%2F%2Fexample.com&sa=t")
+                .unwrap()
+                .0
+                .to_string(),
+            "https://example.com/"
         );
     }
 
@@ -992,11 +2002,13 @@ mod unwrap_exit_params {
         let clink = Clink::new(super::test_config(Mode::Remove));
         assert_eq!(
             clink
-                .try_unwrap_redirect(
+                .parse_link(
                     "https://www.google.com/url?q=https%3A%2F%2Fexample.com&sa=t&usg=abc123"
                 )
-                .0,
-            "https://example.com"
+                .unwrap()
+                .0
+                .to_string(),
+            "https://example.com/"
         );
     }
 
@@ -1005,9 +2017,11 @@ mod unwrap_exit_params {
         let clink = Clink::new(super::test_config(Mode::Remove));
         assert_eq!(
             clink
-                .try_unwrap_redirect("https://bing.com/ck/a?u=https%3A%2F%2Fexample.com&foo=bar")
-                .0,
-            "https://example.com"
+                .parse_link("https://bing.com/ck/a?u=https%3A%2F%2Fexample.com&foo=bar")
+                .unwrap()
+                .0
+                .to_string(),
+            "https://example.com/"
         );
     }
 
@@ -1024,6 +2038,23 @@ mod unwrap_exit_params {
         );
     }
 
+    #[test]
+    fn amazon_ref_path_segment_stripped() {
+        let clink = Clink::new(super::test_config(Mode::Remove));
+        let result = clink.find_and_replace(
+            "https://www.amazon.com/Some-Product/dp/B08N5WRWNW/ref=sr_1_3?psc=1&keep=me",
+        );
+        assert_eq!(
+            result.text,
+            "https://www.amazon.com/Some-Product/dp/B08N5WRWNW?keep=me"
+        );
+        assert_eq!(result.urls_cleaned, 1);
+        assert_eq!(
+            result.params_removed, 1,
+            "a raw-rule removal is not a query param"
+        );
+    }
+
     #[test]
     fn youtube_music_si_stripped() {
         let clink = Clink::new(super::test_config(Mode::Remove));
@@ -1041,12 +2072,151 @@ mod unwrap_exit_params {
     fn has_exit_url_but_no_exit_param() {
         let clink = Clink::new(super::test_config(Mode::Remove));
         assert_eq!(
-            clink.try_unwrap_redirect("https://exit.sc/?foo=bar").0,
+            clink
+                .parse_link("https://exit.sc/?foo=bar")
+                .unwrap()
+                .0
+                .to_string(),
             "https://exit.sc/?foo=bar"
         );
     }
 }
 
+#[cfg(test)]
+mod explain {
+    use super::*;
+
+    #[test]
+    fn records_each_hop_of_a_chained_redirect() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let input = "https://exit.sc/?url=https%3A%2F%2Fwww.google.com%2Furl%3Fq%3Dhttps%253A%252F%252Fexample.com%252F%253Fkeep%253D1";
+        let reports = clink.explain(input);
+        assert_eq!(reports.len(), 1);
+        let hops: Vec<&str> = reports[0]
+            .redirects
+            .iter()
+            .map(|h| h.provider.as_str())
+            .collect();
+        assert_eq!(hops, vec!["exitsc", "google"]);
+        assert_eq!(reports[0].redirects[1].to, "https://example.com/?keep=1");
+        assert_eq!(reports[0].cleaned, "https://example.com/?keep=1");
+    }
+
+    #[test]
+    fn global_rule_is_attributed_to_global() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let reports = clink.explain("https://test.test/?fbclid=a&keep=b");
+        let params = &reports[0].params;
+        assert_eq!(params[0].key, "fbclid");
+        assert_eq!(params[0].tracked_by.as_ref().unwrap().provider, "global");
+        assert!(
+            params[1].tracked_by.is_none(),
+            "keep must be reported as kept"
+        );
+    }
+
+    #[test]
+    fn params_are_traced_as_the_cleaner_parses_them() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        let input = "https://test.test/?%66bclid=a&keep=b&&fbclid=c#/p?utm_source=d";
+        let reports = clink.explain(input);
+        let params: Vec<(&str, bool, bool)> = reports[0]
+            .params
+            .iter()
+            .map(|p| (p.key.as_str(), p.fragment, p.tracked_by.is_some()))
+            .collect();
+        assert_eq!(
+            params,
+            [
+                ("fbclid", false, true),
+                ("keep", false, false),
+                ("fbclid", false, true),
+                ("utm_source", true, true),
+            ]
+        );
+        assert_eq!(reports[0].cleaned, "https://test.test/?keep=b#/p");
+    }
+
+    #[test]
+    fn raw_rule_hits_are_recorded_before_params() {
+        let mut cfg = test_config(Mode::Remove);
+        cfg.providers
+            .entry("global".to_string())
+            .or_default()
+            .raw_rules
+            .push(";jsessionid=[^?#]*".to_string());
+        let clink = Clink::new(cfg);
+        let reports = clink.explain("https://test.test/page;jsessionid=ABC?fbclid=a");
+        assert_eq!(reports[0].raw_rules.len(), 1);
+        assert_eq!(reports[0].raw_rules[0].provider, "global");
+        assert_eq!(reports[0].raw_rules[0].rule, ";jsessionid=[^?#]*");
+        assert_eq!(reports[0].cleaned, "https://test.test/page");
+    }
+
+    #[test]
+    fn global_exception_shields_global_rules_only() {
+        let mut cfg = test_config(Mode::Remove);
+        cfg.providers
+            .entry("global".to_string())
+            .or_default()
+            .exceptions
+            .push(r"^https?://intranet\.test/".to_string());
+        cfg.providers.insert(
+            "intranet".to_string(),
+            ProviderConfig {
+                url_pattern: Some(r"^https?://intranet\.test/".to_string()),
+                rules: vec!["sid".to_string()],
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(cfg);
+        assert_eq!(
+            clink
+                .find_and_replace("https://intranet.test/a?utm_source=x&sid=1")
+                .text,
+            "https://intranet.test/a?utm_source=x"
+        );
+        assert_eq!(
+            clink
+                .find_and_replace("https://test.test/a?utm_source=x")
+                .text,
+            "https://test.test/a"
+        );
+
+        let reports = clink.explain("https://intranet.test/a?utm_source=x");
+        let global = reports[0]
+            .providers
+            .iter()
+            .find(|p| p.provider == "global")
+            .unwrap();
+        assert_eq!(
+            global.excluded_by.as_deref(),
+            Some(r"^https?://intranet\.test/")
+        );
+    }
+
+    #[test]
+    fn global_redirection_runs_after_scoped_ones() {
+        let mut cfg = test_config(Mode::Remove);
+        cfg.providers
+            .entry("global".to_string())
+            .or_default()
+            .redirections
+            .push(r"[?&]redirect_to=([^&]+)".to_string());
+        let clink = Clink::new(cfg);
+        let reports =
+            clink.explain("https://sso.test/login?redirect_to=https%3A%2F%2Fexample.com%2F");
+        assert_eq!(reports[0].cleaned, "https://example.com/");
+        assert_eq!(reports[0].redirects[0].provider, "global");
+    }
+
+    #[test]
+    fn unparseable_link_is_skipped() {
+        let clink = Clink::new(test_config(Mode::Remove));
+        assert!(clink.explain("https://example.com:65536/foo").is_empty());
+    }
+}
+
 #[cfg(test)]
 mod swap {
     use super::*;
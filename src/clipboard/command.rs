@@ -0,0 +1,316 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{ClipboardBackend, CommandConfig, SENSITIVE_TARGETS};
+use crate::config::Selection;
+use crate::flavors::Flavors;
+
+// A clipboard tool that hangs (e.g. waiting for a compositor that went away)
+// must not stall the daemon loop.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// Reads and writes the clipboard through external tools such as
+// `wl-paste`/`wl-copy`. Plain text only, and always polled.
+pub struct CommandClipboard {
+    name: String,
+    config: CommandConfig,
+}
+
+fn args(argv: &[&str]) -> Vec<String> {
+    argv.iter().map(ToString::to_string).collect()
+}
+
+impl CommandClipboard {
+    pub fn new(config: CommandConfig) -> Result<Self, String> {
+        for (key, argv) in [
+            ("get", Some(&config.get)),
+            ("set", Some(&config.set)),
+            ("get_primary", config.get_primary.as_ref()),
+            ("set_primary", config.set_primary.as_ref()),
+            ("targets", config.targets.as_ref()),
+            ("targets_primary", config.targets_primary.as_ref()),
+        ] {
+            if argv.is_some_and(Vec::is_empty) {
+                return Err(format!("[clipboard] {key} is an empty command"));
+            }
+        }
+        let name = format!("command: {}", config.get[0]);
+        Ok(Self { name, config })
+    }
+
+    fn preset(name: &str, config: CommandConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    pub fn wl_clipboard() -> Self {
+        Self::preset(
+            "wl-clipboard",
+            CommandConfig {
+                get: args(&["wl-paste", "--no-newline"]),
+                set: args(&["wl-copy"]),
+                get_primary: Some(args(&["wl-paste", "--primary", "--no-newline"])),
+                set_primary: Some(args(&["wl-copy", "--primary"])),
+                targets: Some(args(&["wl-paste", "--list-types"])),
+                targets_primary: Some(args(&["wl-paste", "--primary", "--list-types"])),
+            },
+        )
+    }
+
+    pub fn xclip() -> Self {
+        Self::preset(
+            "xclip",
+            CommandConfig {
+                get: args(&["xclip", "-selection", "clipboard", "-out"]),
+                set: args(&["xclip", "-selection", "clipboard", "-in"]),
+                get_primary: Some(args(&["xclip", "-selection", "primary", "-out"])),
+                set_primary: Some(args(&["xclip", "-selection", "primary", "-in"])),
+                targets: Some(args(&[
+                    "xclip",
+                    "-selection",
+                    "clipboard",
+                    "-target",
+                    "TARGETS",
+                    "-out",
+                ])),
+                targets_primary: Some(args(&[
+                    "xclip",
+                    "-selection",
+                    "primary",
+                    "-target",
+                    "TARGETS",
+                    "-out",
+                ])),
+            },
+        )
+    }
+
+    pub fn xsel() -> Self {
+        Self::preset(
+            "xsel",
+            CommandConfig {
+                get: args(&["xsel", "--clipboard", "--output"]),
+                set: args(&["xsel", "--clipboard", "--input"]),
+                get_primary: Some(args(&["xsel", "--primary", "--output"])),
+                set_primary: Some(args(&["xsel", "--primary", "--input"])),
+                // xsel can't list targets.
+                targets: None,
+                targets_primary: None,
+            },
+        )
+    }
+
+    fn commands(&self, selection: Selection) -> Option<(&[String], &[String])> {
+        match selection {
+            Selection::Clipboard => Some((&self.config.get, &self.config.set)),
+            Selection::Primary => self
+                .config
+                .get_primary
+                .as_deref()
+                .zip(self.config.set_primary.as_deref()),
+        }
+    }
+}
+
+impl ClipboardBackend for CommandClipboard {
+    fn name(&self, _watching: bool) -> String {
+        format!("{} (polling)", self.name)
+    }
+
+    fn supports(&self, selection: Selection) -> bool {
+        self.commands(selection).is_some()
+    }
+
+    // A failing `targets` command (e.g. an empty clipboard) says nothing
+    // about the content, so it doesn't block cleaning.
+    fn is_sensitive(&mut self, selection: Selection) -> bool {
+        let targets = match selection {
+            Selection::Clipboard => self.config.targets.as_deref(),
+            Selection::Primary => self.config.targets_primary.as_deref(),
+        };
+        targets
+            .and_then(|argv| run(argv, None).ok())
+            .is_some_and(|listed| {
+                listed
+                    .lines()
+                    .any(|target| SENSITIVE_TARGETS.contains(&target.trim()))
+            })
+    }
+
+    fn get_text(&mut self, selection: Selection) -> Result<String, String> {
+        let (get, _) = self
+            .commands(selection)
+            .ok_or_else(|| format!("no command configured for {selection}"))?;
+        run(get, None)
+    }
+
+    fn set_flavors(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String> {
+        let (_, set) = self
+            .commands(selection)
+            .ok_or_else(|| format!("no command configured for {selection}"))?;
+        run(set, Some(flavors.text.clone())).map(drop)
+    }
+}
+
+// Runs `argv` without a shell. With `input` the command gets it on stdin and
+// its stdout is discarded: `wl-copy` and `xclip -in` fork a child that keeps
+// serving the selection, and a piped stdout would stay open as long as it
+// lives. Without `input`, stdout is returned.
+fn run(argv: &[String], input: Option<String>) -> Result<String, String> {
+    let (program, rest) = argv
+        .split_first()
+        .ok_or_else(|| "empty command".to_string())?;
+    let writing = input.is_some();
+    let mut child = Command::new(program)
+        .args(rest)
+        .stdin(if writing {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(if writing {
+            Stdio::null()
+        } else {
+            Stdio::piped()
+        })
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+
+    // Both pipes are serviced on their own threads so a command that
+    // produces or expects more than a pipe buffer can't deadlock us.
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+    let reader = child.stdout.take().map(|mut stdout| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stdout.read_to_end(&mut buf);
+            buf
+        })
+    });
+
+    let deadline = Instant::now() + COMMAND_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{program} timed out"));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("Failed to wait for {program}: {e}")),
+        }
+    };
+    if !status.success() {
+        return Err(format!("{program} failed ({status})"));
+    }
+    let bytes = reader.and_then(|r| r.join().ok()).unwrap_or_default();
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn file_backed(path: &Path) -> CommandClipboard {
+        let path = path.display().to_string();
+        CommandClipboard::new(CommandConfig {
+            get: args(&["cat", &path]),
+            set: vec!["sh".into(), "-c".into(), format!("cat > '{path}'")],
+            get_primary: None,
+            set_primary: None,
+            targets: Some(vec![
+                "sh".into(),
+                "-c".into(),
+                format!("cat '{path}.targets'"),
+            ]),
+            targets_primary: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_and_writes_through_commands() {
+        let dir = std::env::temp_dir().join("clink_test_command_clipboard");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clipboard");
+        std::fs::write(&path, "https://a.test/?fbclid=1").unwrap();
+
+        let mut clipboard = file_backed(&path);
+        assert_eq!(
+            clipboard.get_text(Selection::Clipboard).unwrap(),
+            "https://a.test/?fbclid=1"
+        );
+        clipboard
+            .set_flavors(
+                Selection::Clipboard,
+                &Flavors::text("https://a.test/".into()),
+            )
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "https://a.test/");
+        assert!(!clipboard.supports(Selection::Primary));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn password_manager_hint_marks_content_sensitive() {
+        let dir = std::env::temp_dir().join("clink_test_command_clipboard_hint");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clipboard");
+        let mut clipboard = file_backed(&path);
+        // No targets listed (the command fails): not sensitive.
+        assert!(!clipboard.is_sensitive(Selection::Clipboard));
+
+        let targets = dir.join("clipboard.targets");
+        std::fs::write(&targets, "text/plain\nUTF8_STRING\n").unwrap();
+        assert!(!clipboard.is_sensitive(Selection::Clipboard));
+        std::fs::write(&targets, "text/plain\nx-kde-passwordManagerHint\n").unwrap();
+        assert!(clipboard.is_sensitive(Selection::Clipboard));
+        assert!(!clipboard.is_sensitive(Selection::Primary));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failing_and_empty_commands_are_errors() {
+        assert!(run(&args(&["false"]), None).is_err());
+        assert!(run(&args(&["clink-no-such-tool"]), None).is_err());
+        assert!(
+            CommandClipboard::new(CommandConfig {
+                get: Vec::new(),
+                set: args(&["true"]),
+                get_primary: None,
+                set_primary: None,
+                targets: None,
+                targets_primary: None,
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn presets_cover_primary() {
+        for preset in [
+            CommandClipboard::wl_clipboard(),
+            CommandClipboard::xclip(),
+            CommandClipboard::xsel(),
+        ] {
+            assert!(preset.supports(Selection::Primary));
+        }
+    }
+}
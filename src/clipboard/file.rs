@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::{ClipboardBackend, ClipboardEvent, FileConfig};
+use crate::config::Selection;
+use crate::flavors::Flavors;
+use crate::runtime;
+
+type Watcher = Arc<Mutex<Option<Sender<ClipboardEvent>>>>;
+
+// A file standing in for the clipboard, for headless setups and tests. A
+// regular file is polled and cleaned in place. A FIFO is read on a
+// background thread: every writer that opens it, writes and closes it
+// replaces the "clipboard", and the loop is woken right away.
+pub struct FileClipboard {
+    path: PathBuf,
+    fifo: Option<Fifo>,
+}
+
+struct Fifo {
+    output: Option<PathBuf>,
+    current: Arc<Mutex<String>>,
+    watcher: Watcher,
+}
+
+impl FileClipboard {
+    pub fn new(config: &FileConfig) -> Result<Self, String> {
+        let path = config.path.clone();
+        if !is_fifo(&path) {
+            if config.output.is_some() {
+                return Err(format!(
+                    "[clipboard] output is only used when path is a FIFO, {} is not one",
+                    path.display()
+                ));
+            }
+            return Ok(Self { path, fifo: None });
+        }
+
+        let current = Arc::new(Mutex::new(String::new()));
+        let watcher: Watcher = Arc::default();
+        let read_path = path.clone();
+        let read_current = Arc::clone(&current);
+        let read_watcher = Arc::clone(&watcher);
+        thread::spawn(move || read_fifo(&read_path, &read_current, &read_watcher));
+
+        Ok(Self {
+            path,
+            fifo: Some(Fifo {
+                output: config.output.clone(),
+                current,
+                watcher,
+            }),
+        })
+    }
+}
+
+impl ClipboardBackend for FileClipboard {
+    fn name(&self, watching: bool) -> String {
+        let how = if watching { "fifo events" } else { "polling" };
+        format!("file: {} ({how})", self.path.display())
+    }
+
+    fn watch(&self, events: Sender<ClipboardEvent>) -> Result<(), String> {
+        let Some(fifo) = &self.fifo else {
+            return Err("a regular file has no change notifications".to_string());
+        };
+        *fifo
+            .watcher
+            .lock()
+            .map_err(|_| "FIFO reader state poisoned".to_string())? = Some(events);
+        Ok(())
+    }
+
+    fn get_text(&mut self, selection: Selection) -> Result<String, String> {
+        if selection != Selection::Clipboard {
+            return Err(format!("the file backend can't read {selection}"));
+        }
+        match &self.fifo {
+            Some(fifo) => fifo
+                .current
+                .lock()
+                .map(|current| current.clone())
+                .map_err(|_| "FIFO reader state poisoned".to_string()),
+            None => fs::read_to_string(&self.path)
+                .map_err(|e| format!("Failed to read {}: {e}", self.path.display())),
+        }
+    }
+
+    fn set_flavors(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String> {
+        if selection != Selection::Clipboard {
+            return Err(format!("the file backend can't write {selection}"));
+        }
+        let Some(fifo) = &self.fifo else {
+            return runtime::write_atomic(&self.path, &flavors.text);
+        };
+        fifo.current
+            .lock()
+            .map_err(|_| "FIFO reader state poisoned".to_string())?
+            .clone_from(&flavors.text);
+        match &fifo.output {
+            Some(output) => runtime::write_atomic(output, &flavors.text),
+            None => Ok(()),
+        }
+    }
+}
+
+// Opening a FIFO for reading blocks until a writer shows up, and reading
+// returns everything written until the last writer closes it.
+fn read_fifo(path: &Path, current: &Mutex<String>, watcher: &Watcher) {
+    let reason = loop {
+        match fs::read_to_string(path) {
+            // A writer that opened and closed without writing anything.
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => {
+                let Ok(mut current) = current.lock() else {
+                    break "FIFO reader state poisoned".to_string();
+                };
+                *current = text;
+                drop(current);
+                if let Ok(watcher) = watcher.lock() {
+                    if let Some(events) = watcher.as_ref() {
+                        let _ = events.send(ClipboardEvent::Changed);
+                    }
+                }
+            }
+            Err(e) => break format!("Failed to read {}: {e}", path.display()),
+        }
+    };
+    if let Ok(watcher) = watcher.lock() {
+        if let Some(events) = watcher.as_ref() {
+            let _ = events.send(ClipboardEvent::WatchStopped(reason));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn regular_file_is_cleaned_in_place() {
+        let dir = test_dir("clink_test_file_clipboard");
+        let path = dir.join("clipboard");
+        let mut clipboard = FileClipboard::new(&FileConfig {
+            path: path.clone(),
+            output: None,
+        })
+        .unwrap();
+        assert!(clipboard.get_text(Selection::Clipboard).is_err());
+
+        fs::write(&path, "copied").unwrap();
+        assert_eq!(clipboard.get_text(Selection::Clipboard).unwrap(), "copied");
+        clipboard
+            .set_flavors(Selection::Clipboard, &Flavors::text("cleaned".into()))
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "cleaned");
+        assert!(clipboard.watch(std::sync::mpsc::channel().0).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn output_needs_a_fifo() {
+        let dir = test_dir("clink_test_file_clipboard_output");
+        assert!(
+            FileClipboard::new(&FileConfig {
+                path: dir.join("clipboard"),
+                output: Some(dir.join("out")),
+            })
+            .is_err()
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifo_writes_wake_the_watcher() {
+        let dir = test_dir("clink_test_fifo_clipboard");
+        let path = dir.join("clipboard");
+        let output = dir.join("cleaned");
+        assert!(
+            std::process::Command::new("mkfifo")
+                .arg(&path)
+                .status()
+                .unwrap()
+                .success()
+        );
+        let mut clipboard = FileClipboard::new(&FileConfig {
+            path: path.clone(),
+            output: Some(output.clone()),
+        })
+        .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        clipboard.watch(tx).unwrap();
+
+        fs::write(&path, "https://a.test/?fbclid=1").unwrap();
+        assert!(matches!(
+            rx.recv_timeout(std::time::Duration::from_secs(2)),
+            Ok(ClipboardEvent::Changed)
+        ));
+        assert_eq!(
+            clipboard.get_text(Selection::Clipboard).unwrap(),
+            "https://a.test/?fbclid=1"
+        );
+        clipboard
+            .set_flavors(
+                Selection::Clipboard,
+                &Flavors::text("https://a.test/".into()),
+            )
+            .unwrap();
+        assert_eq!(
+            clipboard.get_text(Selection::Clipboard).unwrap(),
+            "https://a.test/"
+        );
+        assert_eq!(fs::read_to_string(&output).unwrap(), "https://a.test/");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,238 @@
+mod command;
+mod file;
+mod plain;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Selection;
+use crate::flavors::Flavors;
+
+use self::command::CommandClipboard;
+use self::file::FileClipboard;
+use self::plain::PlainClipboard;
+#[cfg(all(unix, not(target_os = "macos")))]
+use self::x11::X11Clipboard;
+
+// Targets password managers (KeePassXC, KDE Wallet, browsers) add next to a
+// copied secret. The only value in use is `secret`, so the target being
+// offered at all is enough.
+pub const SENSITIVE_TARGETS: [&str; 2] = [
+    "x-kde-passwordManagerHint",
+    "application/x-nspasteboard-concealed-type",
+];
+
+// What a clipboard watcher reports to the daemon loop.
+pub enum ClipboardEvent {
+    Changed,
+    // The watcher died (e.g. the X server went away); the loop falls back
+    // to polling.
+    WatchStopped(String),
+}
+
+// The daemon's view of the system clipboard. Backends only need to read and
+// write text; rich flavors, change notifications and PRIMARY are optional.
+pub trait ClipboardBackend {
+    // Shown by `clink state`.
+    fn name(&self, watching: bool) -> String;
+
+    fn supports(&self, selection: Selection) -> bool {
+        selection == Selection::Clipboard
+    }
+
+    // Starts reporting clipboard changes on `events`. Backends without change
+    // notifications return Err and the daemon polls every `sleep_duration`.
+    fn watch(&self, events: Sender<ClipboardEvent>) -> Result<(), String> {
+        drop(events);
+        Err(format!("{} has no change notifications", self.name(false)))
+    }
+
+    // Whether the owner marked the content as a secret, the way password
+    // managers do so clipboard tools leave it alone. Checked before the text
+    // is processed; backends that can't tell say no.
+    fn is_sensitive(&mut self, selection: Selection) -> bool {
+        let _ = selection;
+        false
+    }
+
+    fn get_text(&mut self, selection: Selection) -> Result<String, String>;
+
+    // Called only when the text changed, so the extra round trips for rich
+    // flavors don't happen on every poll.
+    fn get_flavors(&mut self, selection: Selection, text: String) -> Flavors {
+        let _ = selection;
+        Flavors::text(text)
+    }
+
+    fn set_flavors(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String>;
+}
+
+pub type Clipboard = Box<dyn ClipboardBackend>;
+
+// The `[clipboard]` table. `auto` picks the richest backend that works on
+// this machine; the others pin one, which is how clink runs on Wayland
+// without XWayland, in containers, or against a file in tests.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "backend")]
+pub enum ClipboardConfig {
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "x11")]
+    X11,
+    #[serde(rename = "copypasta")]
+    Copypasta,
+    #[serde(rename = "wl-clipboard")]
+    WlClipboard,
+    #[serde(rename = "xclip")]
+    Xclip,
+    #[serde(rename = "xsel")]
+    Xsel,
+    #[serde(rename = "command")]
+    Command(CommandConfig),
+    #[serde(rename = "file")]
+    File(FileConfig),
+}
+
+// Argument vectors, run without a shell. `get` prints the clipboard on
+// stdout, `set` reads the new contents from stdin, and the optional
+// `targets` lists the offered types one per line, which is how
+// password-manager hints are spotted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    pub get: Vec<String>,
+    pub set: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub get_primary: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_primary: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targets_primary: Option<Vec<String>>,
+}
+
+// `path` is a regular file cleaned in place, or a FIFO clink reads each
+// write from. Cleaned FIFO input goes to `output` when set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+impl fmt::Display for ClipboardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClipboardConfig::Auto => write!(f, "auto"),
+            ClipboardConfig::X11 => write!(f, "x11"),
+            ClipboardConfig::Copypasta => write!(f, "copypasta"),
+            ClipboardConfig::WlClipboard => write!(f, "wl-clipboard"),
+            ClipboardConfig::Xclip => write!(f, "xclip"),
+            ClipboardConfig::Xsel => write!(f, "xsel"),
+            ClipboardConfig::Command(c) => write!(f, "command ({})", c.get.join(" ")),
+            ClipboardConfig::File(c) => write!(f, "file ({})", c.path.display()),
+        }
+    }
+}
+
+// Returns the clipboard and, when `auto` had to skip a richer backend,
+// why — the caller decides where that note gets logged.
+pub fn open(config: &ClipboardConfig) -> Result<(Clipboard, Option<String>), String> {
+    let clipboard: Clipboard = match config {
+        ClipboardConfig::Auto => return open_auto(),
+        #[cfg(all(unix, not(target_os = "macos")))]
+        ClipboardConfig::X11 => Box::new(X11Clipboard::new()?),
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        ClipboardConfig::X11 => {
+            return Err("the x11 clipboard backend isn't available on this platform".to_string());
+        }
+        ClipboardConfig::Copypasta => Box::new(PlainClipboard::new()?),
+        ClipboardConfig::WlClipboard => Box::new(CommandClipboard::wl_clipboard()),
+        ClipboardConfig::Xclip => Box::new(CommandClipboard::xclip()),
+        ClipboardConfig::Xsel => Box::new(CommandClipboard::xsel()),
+        ClipboardConfig::Command(c) => Box::new(CommandClipboard::new(c.clone())?),
+        ClipboardConfig::File(c) => Box::new(FileClipboard::new(c)?),
+    };
+    Ok((clipboard, None))
+}
+
+// X11 first, as it is the only backend with rich flavors and change
+// notifications. A Wayland session without XWayland gets wl-clipboard when
+// it is installed; anything else gets copypasta's plain-text clipboard.
+fn open_auto() -> Result<(Clipboard, Option<String>), String> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let skipped = match X11Clipboard::new() {
+        Ok(x11) => return Ok((Box::new(x11), None)),
+        Err(e) => Some(e),
+    };
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    let skipped: Option<String> = None;
+
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() && command::on_path("wl-paste") {
+        let reason = skipped.map(|e| format!("{e}; using wl-clipboard"));
+        return Ok((Box::new(CommandClipboard::wl_clipboard()), reason));
+    }
+    let reason = skipped.map(|e| format!("{e}; falling back to plain-text clipboard"));
+    Ok((Box::new(PlainClipboard::new()?), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClinkConfig;
+
+    fn parse(table: &str) -> ClipboardConfig {
+        let cfg: ClinkConfig = toml::from_str(&format!(
+            "mode = 'remove'\nreplace_to = 'clink'\nsleep_duration = 150\n{table}\n[providers]\n"
+        ))
+        .unwrap();
+        cfg.clipboard
+    }
+
+    #[test]
+    fn backend_defaults_to_auto() {
+        assert_eq!(parse(""), ClipboardConfig::Auto);
+        assert_eq!(
+            parse("[clipboard]\nbackend = 'wl-clipboard'"),
+            ClipboardConfig::WlClipboard
+        );
+    }
+
+    #[test]
+    fn parses_command_and_file_backends() {
+        assert_eq!(
+            parse("[clipboard]\nbackend = 'command'\nget = ['pbpaste']\nset = ['pbcopy']"),
+            ClipboardConfig::Command(CommandConfig {
+                get: vec!["pbpaste".into()],
+                set: vec!["pbcopy".into()],
+                get_primary: None,
+                set_primary: None,
+                targets: None,
+                targets_primary: None,
+            })
+        );
+        let file = parse("[clipboard]\nbackend = 'file'\npath = '/tmp/clip'");
+        assert_eq!(file.to_string(), "file (/tmp/clip)");
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let config = ClinkConfig {
+            clipboard: ClipboardConfig::File(FileConfig {
+                path: "/tmp/in".into(),
+                output: Some("/tmp/out".into()),
+            }),
+            ..ClinkConfig::default()
+        };
+        let loaded: ClinkConfig = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(loaded.clipboard, config.clipboard);
+    }
+}
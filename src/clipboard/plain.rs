@@ -0,0 +1,41 @@
+use copypasta::{ClipboardContext, ClipboardProvider};
+
+use super::ClipboardBackend;
+use crate::config::Selection;
+use crate::flavors::Flavors;
+
+// copypasta's plain-text clipboard, available on every platform clink
+// builds for.
+pub struct PlainClipboard {
+    ctx: ClipboardContext,
+}
+
+impl PlainClipboard {
+    pub fn new() -> Result<Self, String> {
+        let ctx =
+            ClipboardContext::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
+        Ok(Self { ctx })
+    }
+}
+
+impl ClipboardBackend for PlainClipboard {
+    fn name(&self, _watching: bool) -> String {
+        "copypasta (polling)".to_string()
+    }
+
+    fn get_text(&mut self, selection: Selection) -> Result<String, String> {
+        if selection != Selection::Clipboard {
+            return Err(format!("copypasta can't read {selection}"));
+        }
+        self.ctx.get_contents().map_err(|e| e.to_string())
+    }
+
+    fn set_flavors(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String> {
+        if selection != Selection::Clipboard {
+            return Err(format!("copypasta can't write {selection}"));
+        }
+        self.ctx
+            .set_contents(flavors.text.clone())
+            .map_err(|e| e.to_string())
+    }
+}
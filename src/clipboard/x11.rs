@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::Event;
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt, CreateWindowAux, EventMask, PropMode, Property,
+    SELECTION_NOTIFY_EVENT, SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME};
+
+use super::{ClipboardBackend, ClipboardEvent};
+use crate::config::Selection;
+use crate::flavors::Flavors;
+
+// How long to wait for the selection owner to answer one conversion. A busy
+// or hung owner must not stall the daemon loop.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        TEXT,
+        UTF8_STRING,
+        TEXT_PLAIN: b"text/plain",
+        TEXT_PLAIN_UTF8: b"text/plain;charset=utf-8",
+        TEXT_HTML: b"text/html",
+        TEXT_URI_LIST: b"text/uri-list",
+        // Password managers mark secrets with these, see `SENSITIVE_TARGETS`.
+        PASSWORD_MANAGER_HINT: b"x-kde-passwordManagerHint",
+        CONCEALED_TYPE: b"application/x-nspasteboard-concealed-type",
+        CLINK_TRANSFER,
+    }
+}
+
+impl Atoms {
+    // Plain-text targets in order of preference when reading. All of them
+    // are served with the same UTF-8 bytes.
+    fn text_targets(&self) -> [Atom; 4] {
+        [
+            self.UTF8_STRING,
+            self.TEXT_PLAIN_UTF8,
+            self.TEXT_PLAIN,
+            self.TEXT,
+        ]
+    }
+
+    fn selection(&self, selection: Selection) -> Atom {
+        match selection {
+            Selection::Clipboard => self.CLIPBOARD,
+            Selection::Primary => AtomEnum::PRIMARY.into(),
+        }
+    }
+}
+
+// selection -> (target, bytes) pairs clink currently serves.
+type Owned = Arc<Mutex<HashMap<Atom, Vec<(Atom, Vec<u8>)>>>>;
+
+// Talks to the X server directly. copypasta (through x11-clipboard) reads and
+// serves a single UTF8_STRING target, which would drop the text/html and
+// text/uri-list flavors next to the plain text. Reading uses its own window
+// and connection; serving happens on a second connection in a background
+// thread, as the owner has to answer requests whenever a paste happens.
+pub struct X11Clipboard {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+    server: Arc<RustConnection>,
+    server_window: Window,
+    owned: Owned,
+}
+
+fn err(context: &str, e: impl Display) -> String {
+    format!("{context}: {e}")
+}
+
+// Returns the connection, a 1x1 window for transfers, the interned atoms,
+// and the root window of the default screen.
+fn connect() -> Result<(RustConnection, Window, Atoms, Window), String> {
+    let (conn, screen_num) =
+        RustConnection::connect(None).map_err(|e| err("Failed to connect to X11", e))?;
+    let window = conn
+        .generate_id()
+        .map_err(|e| err("Failed to allocate X11 window", e))?;
+    let screen = &conn.setup().roots[screen_num];
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        screen.root_visual,
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )
+    .map_err(|e| err("Failed to create X11 window", e))?;
+    let atoms = Atoms::new(&conn)
+        .map_err(|e| err("Failed to intern X11 atoms", e))?
+        .reply()
+        .map_err(|e| err("Failed to intern X11 atoms", e))?;
+    let root = screen.root;
+    Ok((conn, window, atoms, root))
+}
+
+impl X11Clipboard {
+    pub fn new() -> Result<Self, String> {
+        let (conn, window, atoms, _) = connect()?;
+        let (server, server_window, _, _) = connect()?;
+        let server = Arc::new(server);
+        let owned: Owned = Arc::default();
+
+        let serve_conn = Arc::clone(&server);
+        let serve_owned = Arc::clone(&owned);
+        thread::spawn(move || serve(&serve_conn, atoms, &serve_owned));
+
+        Ok(Self {
+            conn,
+            window,
+            atoms,
+            server,
+            server_window,
+            owned,
+        })
+    }
+
+    fn get_text_from(&self, selection: Atom) -> Result<String, String> {
+        for target in [self.atoms.UTF8_STRING, AtomEnum::STRING.into()] {
+            if let Some(bytes) = self.convert(selection, target)? {
+                return Ok(String::from_utf8_lossy(&bytes).into_owned());
+            }
+        }
+        Err("selection has no text".to_string())
+    }
+
+    fn targets(&self, selection: Atom) -> Vec<Atom> {
+        self.convert(selection, self.atoms.TARGETS)
+            .ok()
+            .flatten()
+            .map(|bytes| {
+                bytes
+                    .chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Asks the owner for `target` and waits for the answer. Ok(None) means
+    // the owner can't provide that target (or there is no owner).
+    fn convert(&self, selection: Atom, target: Atom) -> Result<Option<Vec<u8>>, String> {
+        let property = self.atoms.CLINK_TRANSFER;
+        self.conn
+            .convert_selection(self.window, selection, target, property, CURRENT_TIME)
+            .map_err(|e| err("Failed to request X11 selection", e))?;
+        self.conn
+            .flush()
+            .map_err(|e| err("Failed to request X11 selection", e))?;
+
+        let deadline = Instant::now() + READ_TIMEOUT;
+        loop {
+            let event = self
+                .conn
+                .poll_for_event()
+                .map_err(|e| err("X11 connection failed", e))?;
+            match event {
+                Some(Event::SelectionNotify(e))
+                    if e.selection == selection && e.target == target =>
+                {
+                    if e.property == u32::from(AtomEnum::NONE) {
+                        return Ok(None);
+                    }
+                    return self.read_property(property, deadline).map(Some);
+                }
+                Some(_) => {}
+                None if Instant::now() >= deadline => {
+                    return Err("timed out waiting for the clipboard owner".to_string());
+                }
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+
+    // Reads and deletes the transfer property. Large values arrive with the
+    // INCR protocol: the owner writes chunks, each one answered by deleting
+    // the property, until an empty chunk marks the end.
+    fn read_property(&self, property: Atom, deadline: Instant) -> Result<Vec<u8>, String> {
+        let reply = self
+            .conn
+            .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| err("Failed to read X11 selection", e))?
+            .reply()
+            .map_err(|e| err("Failed to read X11 selection", e))?;
+        if reply.type_ != self.atoms.INCR {
+            return Ok(reply.value);
+        }
+
+        let mut buf = Vec::new();
+        loop {
+            let event = self
+                .conn
+                .poll_for_event()
+                .map_err(|e| err("X11 connection failed", e))?;
+            match event {
+                Some(Event::PropertyNotify(e))
+                    if e.atom == property && e.state == Property::NEW_VALUE =>
+                {
+                    let chunk = self
+                        .conn
+                        .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+                        .map_err(|e| err("Failed to read X11 selection", e))?
+                        .reply()
+                        .map_err(|e| err("Failed to read X11 selection", e))?;
+                    if chunk.value.is_empty() {
+                        return Ok(buf);
+                    }
+                    buf.extend_from_slice(&chunk.value);
+                }
+                Some(_) => {}
+                None if Instant::now() >= deadline => {
+                    return Err("timed out reading a large clipboard value".to_string());
+                }
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+impl ClipboardBackend for X11Clipboard {
+    fn name(&self, watching: bool) -> String {
+        if watching {
+            "x11 (xfixes events)".to_string()
+        } else {
+            "x11 (polling)".to_string()
+        }
+    }
+
+    fn supports(&self, _selection: Selection) -> bool {
+        true
+    }
+
+    // Subscribes to XFixes CLIPBOARD owner changes on a dedicated connection
+    // and reports each one on `events`. Fails when the server lacks XFixes;
+    // the caller then polls instead. PRIMARY isn't watched: while a selection
+    // is being dragged out its text keeps changing under the same owner, so
+    // owner changes say nothing about when it's ready to read.
+    fn watch(&self, events: Sender<ClipboardEvent>) -> Result<(), String> {
+        let (conn, _, _, root) = connect()?;
+        conn.xfixes_query_version(5, 0)
+            .map_err(|e| err("XFixes unavailable", e))?
+            .reply()
+            .map_err(|e| err("XFixes unavailable", e))?;
+        conn.xfixes_select_selection_input(
+            root,
+            self.atoms.CLIPBOARD,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )
+        .map_err(|e| err("Failed to watch X11 selection", e))?
+        .check()
+        .map_err(|e| err("Failed to watch X11 selection", e))?;
+
+        thread::spawn(move || {
+            let reason = loop {
+                match conn.wait_for_event() {
+                    Ok(Event::XfixesSelectionNotify(_)) => {
+                        if events.send(ClipboardEvent::Changed).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => break e.to_string(),
+                }
+            };
+            let _ = events.send(ClipboardEvent::WatchStopped(reason));
+        });
+        Ok(())
+    }
+
+    fn is_sensitive(&mut self, selection: Selection) -> bool {
+        let targets = self.targets(self.atoms.selection(selection));
+        [self.atoms.PASSWORD_MANAGER_HINT, self.atoms.CONCEALED_TYPE]
+            .iter()
+            .any(|hint| targets.contains(hint))
+    }
+
+    fn get_text(&mut self, selection: Selection) -> Result<String, String> {
+        self.get_text_from(self.atoms.selection(selection))
+    }
+
+    fn get_flavors(&mut self, selection: Selection, text: String) -> Flavors {
+        let selection = self.atoms.selection(selection);
+        let targets = self.targets(selection);
+        let read = |target: Atom| {
+            if !targets.contains(&target) {
+                return None;
+            }
+            self.convert(selection, target)
+                .ok()
+                .flatten()
+                .map(|bytes| decode_text(&bytes))
+        };
+        Flavors {
+            text,
+            html: read(self.atoms.TEXT_HTML),
+            uri_list: read(self.atoms.TEXT_URI_LIST),
+        }
+    }
+
+    fn set_flavors(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String> {
+        let selection = self.atoms.selection(selection);
+        let mut entries: Vec<(Atom, Vec<u8>)> = self
+            .atoms
+            .text_targets()
+            .into_iter()
+            .map(|t| (t, flavors.text.clone().into_bytes()))
+            .collect();
+        if let Some(html) = &flavors.html {
+            entries.push((self.atoms.TEXT_HTML, html.clone().into_bytes()));
+        }
+        if let Some(uri_list) = &flavors.uri_list {
+            entries.push((self.atoms.TEXT_URI_LIST, uri_list.clone().into_bytes()));
+        }
+        self.owned
+            .lock()
+            .map_err(|_| "X11 clipboard state poisoned".to_string())?
+            .insert(selection, entries);
+
+        self.server
+            .set_selection_owner(self.server_window, selection, CURRENT_TIME)
+            .map_err(|e| err("Failed to take X11 selection", e))?;
+        let owner = self
+            .server
+            .get_selection_owner(selection)
+            .map_err(|e| err("Failed to query X11 selection owner", e))?
+            .reply()
+            .map_err(|e| err("Failed to query X11 selection owner", e))?
+            .owner;
+        if owner == self.server_window {
+            Ok(())
+        } else {
+            Err("another client took the X11 selection".to_string())
+        }
+    }
+}
+
+// Browsers have put text/html on the clipboard as UTF-16 with a BOM; every
+// other app uses UTF-8. clink always serves UTF-8 back.
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = rest
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// Answers paste requests for the selections clink owns until the process
+// exits. Losing ownership (someone else copied) drops the stored flavors.
+fn serve(conn: &RustConnection, atoms: Atoms, owned: &Owned) {
+    while let Ok(event) = conn.wait_for_event() {
+        match event {
+            Event::SelectionRequest(request) => {
+                let served = owned
+                    .lock()
+                    .is_ok_and(|owned| answer(conn, atoms, &owned, &request));
+                // ICCCM: old clients send None and expect the target as the
+                // property name.
+                let property = match (served, request.property) {
+                    (false, _) => AtomEnum::NONE.into(),
+                    (true, p) if p == u32::from(AtomEnum::NONE) => request.target,
+                    (true, p) => p,
+                };
+                let _ = conn.send_event(
+                    false,
+                    request.requestor,
+                    EventMask::NO_EVENT,
+                    SelectionNotifyEvent {
+                        response_type: SELECTION_NOTIFY_EVENT,
+                        sequence: 0,
+                        time: request.time,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property,
+                    },
+                );
+                let _ = conn.flush();
+            }
+            Event::SelectionClear(e) => {
+                if let Ok(mut owned) = owned.lock() {
+                    owned.remove(&e.selection);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn answer(
+    conn: &RustConnection,
+    atoms: Atoms,
+    owned: &HashMap<Atom, Vec<(Atom, Vec<u8>)>>,
+    request: &SelectionRequestEvent,
+) -> bool {
+    let Some(entries) = owned.get(&request.selection) else {
+        return false;
+    };
+    let property = if request.property == u32::from(AtomEnum::NONE) {
+        request.target
+    } else {
+        request.property
+    };
+    if request.target == atoms.TARGETS {
+        let targets: Vec<Atom> = std::iter::once(atoms.TARGETS)
+            .chain(entries.iter().map(|(t, _)| *t))
+            .collect();
+        return conn
+            .change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )
+            .is_ok();
+    }
+    let Some((target, bytes)) = entries.iter().find(|(t, _)| *t == request.target) else {
+        return false;
+    };
+    // Values beyond one request would need INCR; clipboard text that large
+    // is refused rather than truncated.
+    if bytes.len() + 32 > conn.maximum_request_bytes() {
+        return false;
+    }
+    conn.change_property8(
+        PropMode::REPLACE,
+        request.requestor,
+        property,
+        *target,
+        bytes,
+    )
+    .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs an X server with XFixes, e.g. `xvfb-run cargo test -- --ignored`"]
+    fn serves_every_flavor_and_reports_changes() {
+        let mut clipboard = X11Clipboard::new().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        clipboard.watch(tx).unwrap();
+
+        let flavors = Flavors {
+            text: "https://a.test/".to_string(),
+            html: Some(r#"<a href="https://a.test/">a</a>"#.to_string()),
+            uri_list: Some("https://a.test/\r\n".to_string()),
+        };
+        clipboard
+            .set_flavors(Selection::Clipboard, &flavors)
+            .unwrap();
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(2)),
+            Ok(ClipboardEvent::Changed)
+        ));
+        let text = clipboard.get_text(Selection::Clipboard).unwrap();
+        assert_eq!(clipboard.get_flavors(Selection::Clipboard, text), flavors);
+    }
+
+    #[test]
+    #[ignore = "needs an X server, e.g. `xvfb-run cargo test -- --ignored`"]
+    fn selections_are_owned_independently() {
+        let mut clipboard = X11Clipboard::new().unwrap();
+        clipboard
+            .set_flavors(Selection::Clipboard, &Flavors::text("copied".to_string()))
+            .unwrap();
+        clipboard
+            .set_flavors(Selection::Primary, &Flavors::text("selected".to_string()))
+            .unwrap();
+        assert_eq!(clipboard.get_text(Selection::Clipboard).unwrap(), "copied");
+        assert_eq!(clipboard.get_text(Selection::Primary).unwrap(), "selected");
+    }
+
+    #[test]
+    fn decodes_utf16_html_with_bom() {
+        let bytes: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("<a>é</a>".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(decode_text(&bytes), "<a>é</a>");
+        assert_eq!(decode_text("<a>é</a>".as_bytes()), "<a>é</a>");
+    }
+}
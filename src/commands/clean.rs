@@ -0,0 +1,161 @@
+use crate::clink::Clink;
+use crate::config::load_config;
+use crate::mode::Mode;
+use crate::remote::resolve_patterns;
+use crate::runtime;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+// Exit status when at least one URL was rewritten. 1 is already taken by
+// every command's error path, so scripts can tell "already clean" (0) from
+// "cleaned" (2) from "failed" (1) without diffing input and output.
+pub const EXIT_CHANGED: i32 = 2;
+
+pub fn execute(config_path: &Path, urls: &[String], mode: Option<Mode>) -> Result<bool, String> {
+    let mut cfg = load_config(config_path)?;
+    // `Clink::new` reports on stdout when verbose, which would end up in the
+    // middle of the cleaned text.
+    cfg.verbose = false;
+    if let Some(mode) = mode {
+        cfg.mode = mode;
+    }
+
+    // stdout carries the cleaned text, so diagnostics go to stderr only.
+    for w in resolve_patterns(&mut cfg, &runtime::data_dir()) {
+        eprintln!("clink: warning: {w}");
+    }
+
+    let clink = Clink::new(cfg);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if urls.is_empty() {
+        clean_lines(&clink, io::stdin().lock(), &mut out)
+    } else {
+        clean_args(&clink, urls, &mut out)
+    }
+}
+
+fn clean_args(clink: &Clink, urls: &[String], out: &mut impl Write) -> Result<bool, String> {
+    let mut changed = false;
+    for url in urls {
+        let result = clink.find_and_replace(url);
+        warn(&result.warnings);
+        changed |= result.text != *url;
+        writeln!(out, "{}", result.text).map_err(|e| format!("Failed to write output: {e}"))?;
+    }
+    out.flush()
+        .map_err(|e| format!("Failed to write output: {e}"))?;
+    Ok(changed)
+}
+
+fn warn(warnings: &[String]) {
+    for w in warnings {
+        eprintln!("clink: warning: {w}");
+    }
+}
+
+// Stream line by line rather than reading stdin to the end, so
+// `tail -f log | clink clean` and editor filters see output as it arrives.
+// `read_line` keeps the terminator, which preserves CRLF and a missing
+// final newline byte-for-byte.
+fn clean_lines(
+    clink: &Clink,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<bool, String> {
+    let mut changed = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = input
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read stdin: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        let result = clink.find_and_replace(&line);
+        warn(&result.warnings);
+        changed |= result.text != line;
+        out.write_all(result.text.as_bytes())
+            .and_then(|()| out.flush())
+            .map_err(|e| format!("Failed to write output: {e}"))?;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClinkConfig;
+    use crate::provider::ProviderConfig;
+    use std::collections::HashMap;
+
+    fn test_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            ProviderConfig {
+                rules: vec!["fbclid".into(), "utm_[a-z]+".into()],
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn clean_args_prints_one_line_per_argument() {
+        let clink = test_clink();
+        let mut out = Vec::new();
+        let changed = clean_args(
+            &clink,
+            &[
+                "https://test.test/?fbclid=a&keep=1".to_string(),
+                "https://test.test/".to_string(),
+            ],
+            &mut out,
+        )
+        .unwrap();
+        assert!(changed);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "https://test.test/?keep=1\nhttps://test.test/\n"
+        );
+    }
+
+    #[test]
+    fn clean_args_reports_unchanged() {
+        let clink = test_clink();
+        let mut out = Vec::new();
+        let changed =
+            clean_args(&clink, &["https://test.test/?keep=1".to_string()], &mut out).unwrap();
+        assert!(!changed, "already-clean input must not report a change");
+    }
+
+    #[test]
+    fn clean_lines_preserves_surrounding_text_and_line_endings() {
+        let clink = test_clink();
+        let input =
+            "see https://test.test/?utm_source=x\r\nplain line\nlast https://test.test/?fbclid=1";
+        let mut out = Vec::new();
+        let changed = clean_lines(&clink, input.as_bytes(), &mut out).unwrap();
+        assert!(changed);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "see https://test.test/\r\nplain line\nlast https://test.test/"
+        );
+    }
+
+    #[test]
+    fn clean_lines_empty_input_is_unchanged() {
+        let clink = test_clink();
+        let mut out = Vec::new();
+        let changed = clean_lines(&clink, "".as_bytes(), &mut out).unwrap();
+        assert!(!changed);
+        assert!(out.is_empty());
+    }
+}
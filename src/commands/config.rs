@@ -1,4 +1,5 @@
-use crate::config::{ClinkConfig, load_config};
+use crate::canonicalize::Canonicalize;
+use crate::config::{ClinkConfig, describe_history, describe_selections, load_config};
 use crate::remote::resolve_patterns;
 use crate::runtime;
 use std::collections::HashSet;
@@ -99,7 +100,49 @@ where
         .collect()
 }
 
-fn diff_configs(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -> bool {
+fn collect_mode_overrides(config: &ClinkConfig) -> HashSet<String> {
+    config
+        .providers
+        .iter()
+        .flat_map(|(name, p)| {
+            let mode = p.mode.map(|m| format!("{name}:mode={m}"));
+            let replace_to = p
+                .replace_to
+                .as_ref()
+                .map(|r| format!("{name}:replace_to={r}"));
+            mode.into_iter().chain(replace_to)
+        })
+        .collect()
+}
+
+fn collect_priorities(config: &ClinkConfig) -> HashSet<String> {
+    config
+        .providers
+        .iter()
+        .filter_map(|(name, p)| p.priority.map(|n| format!("{name}:priority={n}")))
+        .collect()
+}
+
+// An empty `keep` list still changes behavior (it keeps nothing), so it gets
+// a marker entry instead of vanishing from the diff.
+fn collect_keep(config: &ClinkConfig) -> HashSet<String> {
+    config
+        .providers
+        .iter()
+        .flat_map(|(name, p)| match p.keep.as_deref() {
+            None => Vec::new(),
+            Some([]) => vec![format!("{name}:<nothing>")],
+            Some(keep) => keep.iter().map(|k| format!("{name}:{k}")).collect(),
+        })
+        .collect()
+}
+
+fn describe_canonicalize(canonicalize: Option<&Canonicalize>) -> String {
+    canonicalize.map_or_else(|| "off".to_string(), ToString::to_string)
+}
+
+// Top-level settings, one `Name: old -> new` line each.
+fn diff_settings(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -> bool {
     let mut has_diff = false;
 
     if loaded.mode != current.mode {
@@ -125,6 +168,95 @@ fn diff_configs(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -
         .unwrap();
     }
 
+    if loaded.selections != current.selections {
+        has_diff = true;
+        writeln!(
+            out,
+            "Selections: {} -> {}",
+            describe_selections(&loaded.selections),
+            describe_selections(&current.selections)
+        )
+        .unwrap();
+    }
+
+    if loaded.clipboard != current.clipboard {
+        has_diff = true;
+        writeln!(
+            out,
+            "Clipboard backend: {} -> {}",
+            loaded.clipboard, current.clipboard
+        )
+        .unwrap();
+    }
+
+    if loaded.sensitive_patterns != current.sensitive_patterns {
+        has_diff = true;
+        writeln!(
+            out,
+            "Sensitive patterns: [{}] -> [{}]",
+            loaded.sensitive_patterns.join(", "),
+            current.sensitive_patterns.join(", ")
+        )
+        .unwrap();
+    }
+
+    if loaded.guards != current.guards {
+        has_diff = true;
+        writeln!(out, "Guards: {} -> {}", loaded.guards, current.guards).unwrap();
+    }
+
+    if loaded.notifications != current.notifications {
+        has_diff = true;
+        writeln!(
+            out,
+            "Notifications: {} -> {}",
+            loaded.notifications, current.notifications
+        )
+        .unwrap();
+    }
+
+    let (loaded_history, current_history) = (describe_history(loaded), describe_history(current));
+    if loaded_history != current_history {
+        has_diff = true;
+        writeln!(out, "History: {loaded_history} -> {current_history}").unwrap();
+    }
+
+    if loaded.complete_provider_action != current.complete_provider_action {
+        has_diff = true;
+        writeln!(
+            out,
+            "Complete provider action: {} -> {}",
+            loaded.complete_provider_action, current.complete_provider_action
+        )
+        .unwrap();
+    }
+    if loaded.complete_provider_placeholder != current.complete_provider_placeholder {
+        has_diff = true;
+        writeln!(
+            out,
+            "Complete provider placeholder: {} -> {}",
+            loaded.complete_provider_placeholder, current.complete_provider_placeholder
+        )
+        .unwrap();
+    }
+
+    if loaded.canonicalize != current.canonicalize {
+        has_diff = true;
+        writeln!(
+            out,
+            "Canonicalize: {} -> {}",
+            describe_canonicalize(loaded.canonicalize.as_ref()),
+            describe_canonicalize(current.canonicalize.as_ref())
+        )
+        .unwrap();
+    }
+
+    has_diff
+}
+
+fn diff_configs(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -> bool {
+    let mut has_diff = diff_settings(loaded, current, out);
+
     let loaded_rules = collect_all(loaded, |p| &p.rules);
     let current_rules = collect_all(current, |p| &p.rules);
 
@@ -175,9 +307,67 @@ fn diff_configs(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -
         }
     }
 
+    has_diff |= diff_provider_settings(loaded, current, out);
+
+    has_diff
+}
+
+// Per-provider settings beyond rules and redirections, each reported as a
+// set of `provider:entry` items.
+fn diff_provider_settings(loaded: &ClinkConfig, current: &ClinkConfig, out: &mut String) -> bool {
+    let mut has_diff = false;
+
+    let loaded_overrides = collect_mode_overrides(loaded);
+    let current_overrides = collect_mode_overrides(current);
+    has_diff |= write_set_diff(out, "Mode overrides", &loaded_overrides, &current_overrides);
+
+    let loaded_keep = collect_keep(loaded);
+    let current_keep = collect_keep(current);
+    has_diff |= write_set_diff(out, "Keep entries", &loaded_keep, &current_keep);
+
+    let loaded_priorities = collect_priorities(loaded);
+    let current_priorities = collect_priorities(current);
+    has_diff |= write_set_diff(out, "Priorities", &loaded_priorities, &current_priorities);
+
+    let loaded_unrules = collect_all(loaded, |p| &p.unrules);
+    let current_unrules = collect_all(current, |p| &p.unrules);
+    has_diff |= write_set_diff(out, "Unrules", &loaded_unrules, &current_unrules);
+
+    let loaded_raw_rules = collect_all(loaded, |p| &p.raw_rules);
+    let current_raw_rules = collect_all(current, |p| &p.raw_rules);
+    has_diff |= write_set_diff(out, "Raw rules", &loaded_raw_rules, &current_raw_rules);
+
     has_diff
 }
 
+// Writes sorted added/removed sections for a set of `provider:entry` items.
+// Returns whether anything differed.
+fn write_set_diff(
+    out: &mut String,
+    label: &str,
+    loaded: &HashSet<String>,
+    current: &HashSet<String>,
+) -> bool {
+    let mut added: Vec<&String> = current.difference(loaded).collect();
+    let mut removed: Vec<&String> = loaded.difference(current).collect();
+    added.sort();
+    removed.sort();
+
+    if !added.is_empty() {
+        writeln!(out, "\n{label} added ({}):", added.len()).unwrap();
+        for r in &added {
+            writeln!(out, "  + {r}").unwrap();
+        }
+    }
+    if !removed.is_empty() {
+        writeln!(out, "\n{label} removed ({}):", removed.len()).unwrap();
+        for r in &removed {
+            writeln!(out, "  - {r}").unwrap();
+        }
+    }
+    !added.is_empty() || !removed.is_empty()
+}
+
 fn do_reset(config_path: &Path) -> Result<(), String> {
     eprint!(
         "\nThis will overwrite {} with default config. Continue? [y/N] ",
@@ -205,7 +395,7 @@ fn do_reset(config_path: &Path) -> Result<(), String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::ClinkConfig;
+    use crate::config::{ClinkConfig, Selection};
     use std::collections::HashMap;
 
     #[test]
@@ -305,6 +495,156 @@ mod tests {
         assert!(out.contains("gclid"));
     }
 
+    #[test]
+    fn test_diff_raw_rules() {
+        let mut loaded_providers = HashMap::new();
+        loaded_providers.insert(
+            "amazon".to_string(),
+            crate::provider::ProviderConfig {
+                raw_rules: vec!["/ref=[^/?]*".into()],
+                ..Default::default()
+            },
+        );
+        let loaded = ClinkConfig {
+            providers: loaded_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut current_providers = HashMap::new();
+        current_providers.insert(
+            "amazon".to_string(),
+            crate::provider::ProviderConfig {
+                raw_rules: vec!["/gp/slredirect".into()],
+                ..Default::default()
+            },
+        );
+        let current = ClinkConfig {
+            providers: current_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut out = String::new();
+        let changed = diff_configs(&loaded, &current, &mut out);
+        assert!(changed);
+        assert!(
+            out.contains("Raw rules added (1):\n  + amazon:/gp/slredirect"),
+            "{out}"
+        );
+        assert!(
+            out.contains("Raw rules removed (1):\n  - amazon:/ref=[^/?]*"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_diff_mode_overrides() {
+        let loaded = ClinkConfig::default();
+        let mut current_providers = HashMap::new();
+        current_providers.insert(
+            "analytics".to_string(),
+            crate::provider::ProviderConfig {
+                url_pattern: Some(r"^https?://stats\.corp".into()),
+                mode: Some(crate::mode::Mode::Replace),
+                replace_to: Some("redacted".into()),
+                ..Default::default()
+            },
+        );
+        let current = ClinkConfig {
+            providers: current_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(out.contains("Mode overrides added (2):"), "{out}");
+        assert!(out.contains("+ analytics:mode=Replace"), "{out}");
+        assert!(out.contains("+ analytics:replace_to=redacted"), "{out}");
+    }
+
+    #[test]
+    fn test_diff_keep_lists() {
+        let mut loaded_providers = HashMap::new();
+        loaded_providers.insert(
+            "youtube".to_string(),
+            crate::provider::ProviderConfig {
+                keep: Some(vec!["v".into(), "t".into()]),
+                ..Default::default()
+            },
+        );
+        let loaded = ClinkConfig {
+            providers: loaded_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut current_providers = HashMap::new();
+        current_providers.insert(
+            "youtube".to_string(),
+            crate::provider::ProviderConfig {
+                keep: Some(vec!["v".into(), "list".into()]),
+                ..Default::default()
+            },
+        );
+        current_providers.insert(
+            "amazon".to_string(),
+            crate::provider::ProviderConfig {
+                keep: Some(vec![]),
+                ..Default::default()
+            },
+        );
+        let current = ClinkConfig {
+            providers: current_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(out.contains("+ amazon:<nothing>"), "{out}");
+        assert!(out.contains("+ youtube:list"), "{out}");
+        assert!(out.contains("- youtube:t"), "{out}");
+    }
+
+    #[test]
+    fn test_diff_unrules() {
+        let loaded = ClinkConfig::default();
+        let mut current_providers = HashMap::new();
+        current_providers.insert(
+            "amazon".to_string(),
+            crate::provider::ProviderConfig {
+                unrules: vec!["t".into()],
+                ..Default::default()
+            },
+        );
+        let current = ClinkConfig {
+            providers: current_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(out.contains("Unrules added (1):\n  + amazon:t"), "{out}");
+    }
+
+    #[test]
+    fn test_diff_priorities() {
+        let loaded = ClinkConfig::default();
+        let mut current_providers = HashMap::new();
+        current_providers.insert(
+            "google".to_string(),
+            crate::provider::ProviderConfig {
+                priority: Some(300),
+                ..Default::default()
+            },
+        );
+        let current = ClinkConfig {
+            providers: current_providers,
+            ..ClinkConfig::default()
+        };
+
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(out.contains("+ google:priority=300"), "{out}");
+    }
+
     #[test]
     fn test_diff_no_changes() {
         let mut providers = HashMap::new();
@@ -331,8 +671,10 @@ mod tests {
     #[test]
     fn test_diff_mode_change() {
         let loaded = ClinkConfig::default();
-        let mut current = ClinkConfig::default();
-        current.mode = crate::mode::Mode::Replace;
+        let current = ClinkConfig {
+            mode: crate::mode::Mode::Replace,
+            ..ClinkConfig::default()
+        };
         let mut out = String::new();
         let changed = diff_configs(&loaded, &current, &mut out);
         assert!(changed);
@@ -342,11 +684,43 @@ mod tests {
     #[test]
     fn test_diff_sleep_duration_change() {
         let loaded = ClinkConfig::default();
-        let mut current = ClinkConfig::default();
-        current.sleep_duration = 500;
+        let current = ClinkConfig {
+            sleep_duration: 500,
+            ..ClinkConfig::default()
+        };
         let mut out = String::new();
         let changed = diff_configs(&loaded, &current, &mut out);
         assert!(changed);
         assert!(out.contains("Sleep duration:"));
     }
+
+    #[test]
+    fn test_diff_selections_change() {
+        let loaded = ClinkConfig::default();
+        let current = ClinkConfig {
+            selections: vec![Selection::Clipboard, Selection::Primary],
+            ..ClinkConfig::default()
+        };
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(
+            out.contains("Selections: clipboard -> clipboard, primary"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_diff_canonicalize_change() {
+        let loaded = ClinkConfig::default();
+        let current = ClinkConfig {
+            canonicalize: Some(Canonicalize::default()),
+            ..ClinkConfig::default()
+        };
+        let mut out = String::new();
+        assert!(diff_configs(&loaded, &current, &mut out));
+        assert!(
+            out.contains("Canonicalize: off -> on (dedupe_query, strip_index)"),
+            "{out}"
+        );
+    }
 }
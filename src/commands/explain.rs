@@ -0,0 +1,246 @@
+use crate::clink::{Clink, LinkReport};
+use crate::config::load_config;
+use crate::provider::RuleKind;
+use crate::remote::resolve_patterns;
+use crate::runtime;
+use std::fmt::Write as _;
+use std::path::Path;
+
+pub fn execute(config_path: &Path, urls: &[String], json: bool) -> Result<(), String> {
+    let mut cfg = load_config(config_path)?;
+    // Same as `clink clean`: stdout is the report, `--json` included.
+    cfg.verbose = false;
+    for w in resolve_patterns(&mut cfg, &runtime::data_dir()) {
+        eprintln!("clink: warning: {w}");
+    }
+    let clink = Clink::new(cfg);
+
+    let reports: Vec<LinkReport> = urls.iter().flat_map(|u| clink.explain(u)).collect();
+    if reports.is_empty() {
+        return Err("No parseable URLs found in input.".to_string());
+    }
+
+    if json {
+        let out = serde_json::to_string_pretty(&reports)
+            .map_err(|e| format!("Failed to serialize report: {e}"))?;
+        println!("{out}");
+    } else {
+        let blocks: Vec<String> = reports.iter().map(format_report).collect();
+        print!("{}", blocks.join("\n"));
+    }
+    Ok(())
+}
+
+fn format_report(report: &LinkReport) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}", report.original).unwrap();
+
+    if !report.redirects.is_empty() {
+        writeln!(out, "  Redirects:").unwrap();
+        for hop in &report.redirects {
+            writeln!(out, "    [{}] {} -> {}", hop.provider, hop.from, hop.to).unwrap();
+        }
+    }
+
+    writeln!(out, "  Providers:").unwrap();
+    if report.providers.is_empty() {
+        writeln!(out, "    (none matched, global rules only)").unwrap();
+    }
+    for p in &report.providers {
+        match &p.excluded_by {
+            Some(exception) => {
+                writeln!(
+                    out,
+                    "    {} excluded by exception '{exception}'",
+                    p.provider
+                )
+                .unwrap();
+            }
+            None if p.complete => {
+                writeln!(out, "    {} matched (complete provider)", p.provider).unwrap();
+            }
+            None => writeln!(out, "    {} matched", p.provider).unwrap(),
+        }
+    }
+
+    match &report.mode_from {
+        Some(provider) => writeln!(out, "  Mode: {} (from [{provider}])", report.mode).unwrap(),
+        None => writeln!(out, "  Mode: {}", report.mode).unwrap(),
+    }
+
+    if !report.raw_rules.is_empty() {
+        writeln!(out, "  Raw rules:").unwrap();
+        for hit in &report.raw_rules {
+            writeln!(out, "    [{}] removed '{}'", hit.provider, hit.rule).unwrap();
+        }
+    }
+
+    writeln!(out, "  Params:").unwrap();
+    if report.params.is_empty() {
+        writeln!(out, "    (no query params)").unwrap();
+    }
+    for param in &report.params {
+        let location = if param.fragment { " (fragment)" } else { "" };
+        match &param.tracked_by {
+            Some(t) if t.kind == RuleKind::Allowlist => writeln!(
+                out,
+                "    {}{location} not in [{}] keep list",
+                param.key, t.provider
+            )
+            .unwrap(),
+            Some(t) => writeln!(
+                out,
+                "    {}{location} tracked by [{}] {} rule '{}'",
+                param.key, t.provider, t.kind, t.rule
+            )
+            .unwrap(),
+            None => match &param.unruled_by {
+                Some(u) => writeln!(
+                    out,
+                    "    {}{location} kept by [{}] unrule '{}'",
+                    param.key, u.provider, u.rule
+                )
+                .unwrap(),
+                None => writeln!(out, "    {}{location} kept", param.key).unwrap(),
+            },
+        }
+    }
+
+    if report.canonicalized {
+        writeln!(out, "  Canonicalized").unwrap();
+    }
+    writeln!(out, "  Result: {}", report.cleaned).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClinkConfig;
+    use crate::provider::ProviderConfig;
+    use std::collections::HashMap;
+
+    fn test_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            ProviderConfig {
+                rules: vec!["fbclid".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "shop".to_string(),
+            ProviderConfig {
+                url_pattern: Some(r"^https?://shop\.example".into()),
+                rules: vec!["ref_[a-z]+".into()],
+                raw_rules: vec!["/ref=[^/?]*".into()],
+                exceptions: vec![r"^https?://shop\.example/admin".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "exitsc".to_string(),
+            ProviderConfig {
+                url_pattern: Some(r"^https?://exit\.sc".into()),
+                redirections: vec![r"url=([^&]+)".into()],
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn format_report_lists_redirects_providers_and_params() {
+        let clink = test_clink();
+        let reports = clink.explain(
+            "https://exit.sc/?url=https%3A%2F%2Fshop.example%2Fitem%3Fref_src%3Dx%26fbclid%3Dy%26id%3D1",
+        );
+        let out = format_report(&reports[0]);
+        assert!(out.contains("[exitsc] https://exit.sc/"), "{out}");
+        assert!(out.contains("shop matched"), "{out}");
+        assert!(
+            out.contains("ref_src tracked by [shop] regex rule 'ref_[a-z]+'"),
+            "{out}"
+        );
+        assert!(
+            out.contains("fbclid tracked by [global] literal rule 'fbclid'"),
+            "{out}"
+        );
+        assert!(out.contains("id kept"), "{out}");
+        assert!(
+            out.contains("Result: https://shop.example/item?id=1"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn format_report_shows_exception() {
+        let clink = test_clink();
+        let reports = clink.explain("https://shop.example/admin?ref_src=x");
+        let out = format_report(&reports[0]);
+        assert!(
+            out.contains(r"shop excluded by exception '^https?://shop\.example/admin'"),
+            "{out}"
+        );
+        assert!(out.contains("ref_src kept"), "{out}");
+    }
+
+    #[test]
+    fn format_report_lists_raw_rules() {
+        let clink = test_clink();
+        let reports = clink.explain("https://shop.example/item/ref=sr_1?id=1");
+        let out = format_report(&reports[0]);
+        assert!(out.contains("[shop] removed '/ref=[^/?]*'"), "{out}");
+        assert!(
+            out.contains("Result: https://shop.example/item?id=1"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn format_report_marks_fragment_params() {
+        let clink = test_clink();
+        let reports = clink.explain("https://test.test/#/page?fbclid=x&tab=2");
+        let out = format_report(&reports[0]);
+        assert!(
+            out.contains("fbclid (fragment) tracked by [global] literal rule 'fbclid'"),
+            "{out}"
+        );
+        assert!(out.contains("tab (fragment) kept"), "{out}");
+    }
+
+    #[test]
+    fn format_report_marks_canonicalized_links() {
+        let reports = test_clink().explain("https://Shop.example/item/amp");
+        assert!(!format_report(&reports[0]).contains("Canonicalized"));
+
+        let clink = Clink::new(ClinkConfig {
+            canonicalize: Some(crate::canonicalize::Canonicalize::default()),
+            remote: None,
+            ..ClinkConfig::default()
+        });
+        let reports = clink.explain("https://Shop.example/item/amp");
+        let out = format_report(&reports[0]);
+        assert!(out.contains("Canonicalized"), "{out}");
+        assert!(
+            out.contains("Result: https://shop.example/item/amp"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn report_serializes_to_json() {
+        let clink = test_clink();
+        let reports = clink.explain("https://shop.example/item?ref_src=x");
+        let json: serde_json::Value = serde_json::to_value(&reports).unwrap();
+        assert_eq!(json[0]["cleaned"], "https://shop.example/item");
+        assert_eq!(json[0]["params"][0]["tracked_by"]["provider"], "shop");
+        assert_eq!(json[0]["params"][0]["tracked_by"]["kind"], "regex");
+        assert!(json[0]["providers"][0].get("excluded_by").is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use crate::history::{self, Entry, ExportFormat};
+use crate::runtime;
+
+// Listed entries are cut to this many characters; --export has them whole.
+const PREVIEW_CHARS: usize = 100;
+
+pub fn execute(
+    config_path: &Path,
+    search: Option<&str>,
+    limit: usize,
+    copy: Option<(usize, bool)>,
+    export: Option<ExportFormat>,
+) -> Result<(), String> {
+    let path = runtime::history_file_path();
+    // Newest first, the way they are listed and numbered.
+    let mut entries = history::load(&path);
+    entries.reverse();
+    if let Some(search) = search {
+        entries.retain(|e| e.matches(search));
+    }
+
+    if let Some(format) = export {
+        println!("{}", export_entries(&entries, format)?);
+        return Ok(());
+    }
+    if let Some((n, original)) = copy {
+        let entry = n
+            .checked_sub(1)
+            .and_then(|i| entries.get(i))
+            .ok_or_else(|| format!("No history entry {n}; `clink history` lists them"))?;
+        let text = if original {
+            &entry.original
+        } else {
+            &entry.cleaned
+        };
+        copy_to_clipboard(config_path, text)?;
+        println!("Copied entry {n} to the clipboard.");
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        if crate::config::load_config(config_path).is_ok_and(|cfg| !cfg.history) {
+            println!("History is off (history = false in the config).");
+        } else {
+            println!("No history entries.");
+        }
+        return Ok(());
+    }
+    for (i, entry) in entries.iter().take(limit).enumerate() {
+        println!("{}", format_entry(i + 1, entry));
+    }
+    if entries.len() > limit {
+        println!("({} more, use -n to list them)", entries.len() - limit);
+    }
+    Ok(())
+}
+
+pub fn clear() -> Result<(), String> {
+    history::clear(&runtime::history_file_path())?;
+    println!("History cleared.");
+    Ok(())
+}
+
+fn export_entries(entries: &[Entry], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(entries)
+            .map_err(|e| format!("Failed to serialize history: {e}")),
+        ExportFormat::Csv => Ok(history::to_csv(entries)),
+    }
+}
+
+fn format_entry(n: usize, entry: &Entry) -> String {
+    let time = chrono::DateTime::parse_from_rfc3339(&entry.time).map_or_else(
+        |_| entry.time.clone(),
+        |t| t.format("%Y-%m-%d %H:%M").to_string(),
+    );
+    let flat = entry
+        .cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let preview = match flat.char_indices().nth(PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", &flat[..cut]),
+        None => flat,
+    };
+    format!(
+        "{n:>3}  {time}  {preview}  ({} params removed, {} redirects unwrapped)",
+        entry.params_removed, entry.exits_unwrapped
+    )
+}
+
+// The daemon owns the clipboard: on X11 whatever a short-lived process
+// copies is gone when it exits, and an original copied behind the daemon's
+// back would be cleaned right away.
+#[cfg(unix)]
+fn copy_to_clipboard(_config_path: &Path, text: &str) -> Result<(), String> {
+    crate::control::request(&crate::control::Request::Copy {
+        text: text.to_string(),
+    })
+    .map(drop)
+}
+
+#[cfg(not(unix))]
+fn copy_to_clipboard(config_path: &Path, text: &str) -> Result<(), String> {
+    let cfg = crate::config::load_config(config_path)?;
+    let (mut clipboard, _) = crate::clipboard::open(&cfg.clipboard)?;
+    clipboard.set_flavors(
+        crate::config::Selection::Clipboard,
+        &crate::flavors::Flavors::text(text.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Selection;
+
+    fn entry(cleaned: &str) -> Entry {
+        Entry {
+            time: "2026-04-09T10:05:00+02:00".into(),
+            selection: Selection::Clipboard,
+            original: format!("{cleaned}?utm_source=x"),
+            cleaned: cleaned.into(),
+            urls_cleaned: 1,
+            params_removed: 1,
+            exits_unwrapped: 0,
+        }
+    }
+
+    #[test]
+    fn format_entry_flattens_and_truncates() {
+        assert_eq!(
+            format_entry(1, &entry("https://a.test/\nhttps://b.test/")),
+            "  1  2026-04-09 10:05  https://a.test/ https://b.test/  (1 params removed, 0 redirects unwrapped)"
+        );
+        let long = format!("https://a.test/{}", "x".repeat(200));
+        let line = format_entry(12, &entry(&long));
+        assert!(line.contains(&format!("{}…", &long[..PREVIEW_CHARS])));
+    }
+
+    #[test]
+    fn export_as_json_round_trips() {
+        let entries = vec![entry("https://a.test/"), entry("https://b.test/")];
+        let json = export_entries(&entries, ExportFormat::Json).unwrap();
+        let parsed: Vec<Entry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, entries);
+        assert!(
+            export_entries(&entries, ExportFormat::Csv)
+                .unwrap()
+                .starts_with("time,selection,")
+        );
+    }
+}
@@ -31,7 +31,7 @@ mod tests {
         let _ = std::fs::remove_file(&tmp);
 
         let result = execute(&tmp);
-        assert!(result.is_ok(), "init should succeed: {:?}", result);
+        assert!(result.is_ok(), "init should succeed: {result:?}");
         assert!(tmp.is_file(), "config file should exist");
 
         let content = std::fs::read_to_string(&tmp).unwrap();
@@ -81,8 +81,7 @@ mod tests {
         let result = crate::config::load_config(&tmp);
         assert!(
             result.is_ok(),
-            "template should be a valid config: {:?}",
-            result
+            "template should be a valid config: {result:?}"
         );
 
         let _ = std::fs::remove_file(&tmp);
@@ -1,10 +1,16 @@
+pub mod clean;
 pub mod config;
+pub mod explain;
+pub mod history;
 pub mod init;
 pub mod install;
+pub mod pause;
 pub mod reload;
 pub mod restart;
+pub mod resume;
 pub mod run;
 pub mod state;
+pub mod undo;
 pub mod uninstall;
 pub mod update;
 pub mod validate;
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+pub fn execute(duration: Option<Duration>) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use crate::control::{self, Request};
+        use crate::runtime::DaemonStatus;
+
+        let data = control::request(&Request::Pause {
+            seconds: duration.map(|d| d.as_secs()),
+        })?;
+        let status: DaemonStatus =
+            serde_json::from_value(data).map_err(|e| format!("Invalid status from clink: {e}"))?;
+        match status.paused_until {
+            Some(until) => println!("clink is paused until {until}."),
+            None => println!("clink is paused. Run `clink resume` to clean the clipboard again."),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = duration;
+        Err("Pause is not supported on this platform.".to_string())
+    }
+}
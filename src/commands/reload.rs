@@ -12,8 +12,21 @@ pub fn execute() -> Result<(), String> {
 
     #[cfg(unix)]
     {
+        use crate::control::{Client, ReloadReport, Request};
+
+        // An older daemon, or one that couldn't bind the socket, still
+        // understands SIGHUP; it just can't say how the reload went.
+        if let Ok(client) = Client::connect(&runtime::socket_path()) {
+            let data = client.send(&Request::Reload)?.into_result()?;
+            let report: ReloadReport = serde_json::from_value(data)
+                .map_err(|e| format!("Invalid reload report from clink: {e}"))?;
+            println!("{}", format_report(pid, &report));
+            return Ok(());
+        }
         crate::signal::send_signal(pid, nix::sys::signal::Signal::SIGHUP)?;
-        println!("Sent reload signal to clink (PID {pid}).");
+        println!(
+            "Sent reload signal to clink (PID {pid}). Without its control socket the result only shows up in the log."
+        );
         Ok(())
     }
 
@@ -22,3 +35,43 @@ pub fn execute() -> Result<(), String> {
         Err("Reload is not supported on this platform.".to_string())
     }
 }
+
+#[cfg(unix)]
+fn format_report(pid: u32, report: &crate::control::ReloadReport) -> String {
+    let mut lines = vec![format!(
+        "Reloaded config of clink (PID {pid}): {} providers, {} rules, {} redirections.",
+        report.providers, report.rules, report.redirections
+    )];
+    if !report.warnings.is_empty() {
+        lines.push("\nWarnings:".to_string());
+        lines.extend(report.warnings.iter().map(|w| format!("  - {w}")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::control::ReloadReport;
+
+    #[test]
+    fn format_report_lists_counts_and_warnings() {
+        let mut report = ReloadReport {
+            providers: 3,
+            rules: 12,
+            redirections: 2,
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            format_report(42, &report),
+            "Reloaded config of clink (PID 42): 3 providers, 12 rules, 2 redirections."
+        );
+        report
+            .warnings
+            .push("[providers.x] rule '(' failed to compile".into());
+        assert!(
+            format_report(42, &report)
+                .ends_with("\n\nWarnings:\n  - [providers.x] rule '(' failed to compile")
+        );
+    }
+}
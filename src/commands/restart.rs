@@ -40,8 +40,18 @@ pub fn execute(config_path: &Path, verbose: bool) -> Result<(), String> {
 
 #[cfg(unix)]
 fn stop(pid: u32) -> Result<(), String> {
-    crate::signal::send_signal(pid, nix::sys::signal::Signal::SIGTERM)?;
-    println!("Sent stop signal to clink (PID {pid}).");
+    use crate::control::{Client, Request};
+
+    let acknowledged = Client::connect(&runtime::socket_path())
+        .and_then(|client| client.send(&Request::Shutdown))
+        .and_then(crate::control::Response::into_result)
+        .is_ok();
+    if acknowledged {
+        println!("Asked clink (PID {pid}) to shut down.");
+    } else {
+        crate::signal::send_signal(pid, nix::sys::signal::Signal::SIGTERM)?;
+        println!("Sent stop signal to clink (PID {pid}).");
+    }
 
     for _ in 0..50 {
         if !runtime::is_running(pid) {
@@ -0,0 +1,15 @@
+pub fn execute() -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use crate::control::{self, Request};
+
+        control::request(&Request::Resume)?;
+        println!("clink resumed cleaning the clipboard.");
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        Err("Resume is not supported on this platform.".to_string())
+    }
+}
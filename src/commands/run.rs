@@ -1,13 +1,30 @@
-use crate::clink::Clink;
-use crate::config::{ClinkConfig, load_config};
+use crate::clink::{CleanResult, Clink};
+use crate::clipboard::{self, Clipboard, ClipboardEvent};
+use crate::config::{ClinkConfig, Selection, load_config};
+#[cfg(unix)]
+use crate::control::{self, ReloadReport, Request, Response, UndoReport};
+use crate::flavors::{self, Flavors};
+use crate::guard::{CompiledGuards, Guards};
+use crate::history::History;
+use crate::notify::{Event, Notifications, Notifier};
+use crate::provider::check_provider;
 use crate::runtime;
+use crate::sensitive::{self, SensitivePatterns};
 use crate::stats;
-use copypasta::{ClipboardContext, ClipboardProvider};
-use std::path::Path;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 #[cfg(unix)]
 use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::{thread, time::Duration};
 
+// With change notifications the loop only wakes this often, to notice
+// reload and shutdown signals; clipboard changes wake it immediately.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+// How many rewrites `clink undo` can step back through.
+const UNDO_DEPTH: usize = 10;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 fn validation_log_lines(cfg: &ClinkConfig) -> Vec<String> {
     cfg.validate()
         .into_iter()
@@ -16,10 +33,7 @@ fn validation_log_lines(cfg: &ClinkConfig) -> Vec<String> {
 }
 
 fn log(verbose: bool, msg: &str) {
-    let stamped = format!(
-        "[{}] {msg}",
-        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-    );
+    let stamped = format!("[{}] {msg}", chrono::Local::now().format(TIME_FORMAT));
     if verbose {
         println!("{stamped}");
     }
@@ -35,127 +49,801 @@ fn advance_previous(cleaned: String, old_previous: String, write_failed: bool) -
 }
 
 fn log_err(msg: &str) {
-    let stamped = format!(
-        "[{}] {msg}",
-        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-    );
+    let stamped = format!("[{}] {msg}", chrono::Local::now().format(TIME_FORMAT));
     eprintln!("{stamped}");
     let _ = runtime::append_log(&stamped);
 }
 
-pub fn execute(config_path: &Path, verbose: bool) -> Result<(), String> {
+// Shared by startup and reload: merge remote/builtin patterns, persist the
+// result for `clink config --diff`, and surface every warning in the log.
+// The warnings are returned too, so a reload can report them back.
+fn resolve_and_record(cfg: &mut ClinkConfig) -> Vec<String> {
+    let mut warnings = crate::remote::resolve_patterns(cfg, &runtime::data_dir());
+    for w in &warnings {
+        log_err(w);
+    }
+    if let Err(e) = runtime::write_loaded_config(cfg) {
+        log_err(&format!("Failed to write loaded config: {e}"));
+    }
+    for line in validation_log_lines(cfg) {
+        log_err(&line);
+    }
+    warnings.extend(cfg.validate());
+    // `Clink::new` prints these itself; they're only collected here.
+    let mut provider_warnings: Vec<String> = cfg
+        .providers
+        .iter()
+        .flat_map(|(name, p)| check_provider(name, p))
+        .collect();
+    provider_warnings.sort();
+    warnings.extend(provider_warnings);
+    warnings
+}
+
+pub fn execute(config_path: &Path, verbose: bool, dry_run: bool) -> Result<(), String> {
     runtime::write_pid_file()?;
 
     #[cfg(unix)]
     let signals = crate::signal::install_signal_handlers();
 
-    log(
-        verbose,
-        &format!(
-            "clink {} started (PID {}, config: {})",
-            env!("CARGO_PKG_VERSION"),
-            std::process::id(),
-            config_path.display()
-        ),
-    );
+    log_start(config_path, verbose, dry_run);
 
     let mut cfg: ClinkConfig = load_config(config_path)?;
     cfg.verbose = verbose;
 
-    for w in crate::remote::resolve_patterns(&mut cfg, &runtime::data_dir()) {
-        log_err(&w);
-    }
-
-    if let Err(e) = runtime::write_loaded_config(&cfg) {
-        log_err(&format!("Failed to write loaded config: {e}"));
-    }
-
-    for line in validation_log_lines(&cfg) {
-        log_err(&line);
-    }
+    resolve_and_record(&mut cfg);
 
     if verbose {
         println!("Config ({}):\n {cfg:#?}", config_path.display());
     }
 
     let sleep_duration = Duration::from_millis(cfg.sleep_duration);
+    let (clipboard, fallback_reason) = clipboard::open(&cfg.clipboard)?;
+    if let Some(reason) = fallback_reason {
+        log_err(&reason);
+    }
+    let mut monitor = Monitor::new(clipboard, runtime::stats_file_path());
+    monitor.dry_run = dry_run;
+    monitor.verbose = verbose;
+    monitor.set_selections(&cfg.selections);
+    monitor.sensitive = SensitivePatterns::new(&cfg.sensitive_patterns);
+    monitor.guards = CompiledGuards::new(&cfg.guards);
+    monitor.history = open_history(&cfg);
+    configure_notifier(&mut monitor.notifier, &cfg.notifications);
+    #[cfg(unix)]
+    let backend = cfg.clipboard.clone();
     #[cfg_attr(not(unix), allow(unused_mut))]
     let mut clink = Clink::new(cfg);
-    let mut ctx: ClipboardContext =
-        ClipboardContext::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
-    let mut previous_clipboard = String::new();
-    let stats_path = runtime::stats_file_path();
-    let mut statistics = stats::load(&stats_path);
-    statistics.reset_session();
 
+    let (events_tx, events) = mpsc::channel();
+    let mut watching = start_watching(&monitor.clipboard, events_tx, verbose, sleep_duration);
+    publish_status(&monitor, watching);
+    #[cfg(unix)]
+    let requests = listen_for_requests();
+
+    // Check once at startup: whatever was copied before clink started
+    // doesn't produce a change notification.
+    let mut due = monitor.selections.clone();
     loop {
         #[cfg(unix)]
         {
             if signals.shutdown_requested.load(Ordering::SeqCst) {
-                log(verbose, "clink shutting down (SIGTERM)");
-                let _ = stats::save(&statistics, &stats_path);
-                runtime::remove_pid_file();
-                runtime::remove_loaded_config();
+                shut_down(&monitor, verbose, "SIGTERM");
                 return Ok(());
             }
 
             if signals.reload_requested.load(Ordering::SeqCst) {
                 signals.reload_requested.store(false, Ordering::SeqCst);
-                log(
+                if let Ok((reloaded, _)) = reload(config_path, verbose, &mut monitor, &backend) {
+                    clink = reloaded;
+                    publish_status(&monitor, watching);
+                }
+            }
+
+            while let Some((request, connection)) =
+                requests.as_ref().and_then(|r| r.try_recv().ok())
+            {
+                let mut daemon = Daemon {
+                    config_path,
                     verbose,
-                    &format!("Reloading config from {}", config_path.display()),
-                );
+                    backend: &backend,
+                    monitor: &mut monitor,
+                    clink: &mut clink,
+                    watching,
+                };
+                connection.reply(&daemon.handle(&request));
+                if request == Request::Shutdown {
+                    shut_down(&monitor, verbose, "shutdown request");
+                    return Ok(());
+                }
+            }
+        }
 
-                match load_config(config_path) {
-                    Ok(mut new_cfg) => {
-                        new_cfg.verbose = verbose;
-                        for w in crate::remote::resolve_patterns(&mut new_cfg, &runtime::data_dir())
-                        {
-                            log_err(&w);
-                        }
-                        if let Err(e) = runtime::write_loaded_config(&new_cfg) {
-                            log_err(&format!("Failed to write loaded config: {e}"));
-                        }
-                        for line in validation_log_lines(&new_cfg) {
-                            log_err(&line);
-                        }
-                        clink = Clink::new(new_cfg);
-                        log(verbose, "Config reloaded successfully");
-                    }
-                    Err(e) => {
-                        log_err(&format!("Failed to reload config: {e}"));
+        monitor.handle_notifier_events(verbose);
+        if monitor.pause_expired(chrono::Local::now()) {
+            monitor.resume();
+            log(verbose, "Pause ended, resumed");
+            publish_status(&monitor, watching);
+        }
+        if !monitor.paused {
+            for selection in due {
+                monitor.check(&clink, selection);
+            }
+        }
+        let clipboard_changed = if watching {
+            // PRIMARY is never watched, so it keeps the poll interval.
+            let timeout = if monitor.selections.contains(&Selection::Primary) {
+                sleep_duration.min(SIGNAL_CHECK_INTERVAL)
+            } else {
+                SIGNAL_CHECK_INTERVAL
+            };
+            wait_for_change(&events, &mut watching, &monitor, timeout)
+        } else {
+            thread::sleep(sleep_duration);
+            true
+        };
+        due = due_selections(&monitor.selections, watching, clipboard_changed);
+    }
+}
+
+fn log_start(config_path: &Path, verbose: bool, dry_run: bool) {
+    log(
+        verbose,
+        &format!(
+            "clink {} started (PID {}, config: {}){}",
+            env!("CARGO_PKG_VERSION"),
+            std::process::id(),
+            config_path.display(),
+            if dry_run {
+                ", dry run: changes are only logged, the clipboard is left alone"
+            } else {
+                ""
+            }
+        ),
+    );
+}
+
+fn open_history(cfg: &ClinkConfig) -> Option<History> {
+    let mut history = History::open(runtime::history_file_path(), cfg)?;
+    if let Err(e) = history.prune() {
+        log_err(&format!("Failed to prune history: {e}"));
+    }
+    Some(history)
+}
+
+// Keeps the bus connection across reloads that leave notifications on.
+fn configure_notifier(notifier: &mut Option<Notifier>, cfg: &Notifications) {
+    match (notifier.as_mut(), cfg.enabled) {
+        (Some(notifier), true) => notifier.set_min_interval(cfg.min_interval),
+        (_, false) => *notifier = None,
+        (None, true) => match Notifier::connect(cfg) {
+            Ok(connected) => *notifier = Some(connected),
+            Err(e) => log_err(&format!("Notifications unavailable: {e}")),
+        },
+    }
+}
+
+fn start_watching(
+    clipboard: &Clipboard,
+    events: mpsc::Sender<ClipboardEvent>,
+    verbose: bool,
+    sleep_duration: Duration,
+) -> bool {
+    match clipboard.watch(events) {
+        Ok(()) => true,
+        Err(e) => {
+            log(
+                verbose,
+                &format!(
+                    "Clipboard change notifications unavailable ({e}), polling every {}ms",
+                    sleep_duration.as_millis()
+                ),
+            );
+            false
+        }
+    }
+}
+
+// Signals keep working without the socket, so failing to bind it isn't fatal.
+#[cfg(unix)]
+fn listen_for_requests() -> Option<control::Requests> {
+    match control::listen(&runtime::socket_path()) {
+        Ok(requests) => Some(requests),
+        Err(e) => {
+            log_err(&format!(
+                "Control socket unavailable ({e}), only signals will work"
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shut_down(monitor: &Monitor, verbose: bool, reason: &str) {
+    log(verbose, &format!("clink shutting down ({reason})"));
+    let _ = stats::save(&monitor.statistics, &monitor.stats_path);
+    runtime::remove_socket();
+    runtime::remove_pid_file();
+    runtime::remove_loaded_config();
+    runtime::remove_status();
+}
+
+// Everything a control request may look at or change, borrowed from the
+// loop for the time it takes to answer one.
+#[cfg(unix)]
+struct Daemon<'a> {
+    config_path: &'a Path,
+    verbose: bool,
+    backend: &'a clipboard::ClipboardConfig,
+    monitor: &'a mut Monitor,
+    clink: &'a mut Clink,
+    watching: bool,
+}
+
+#[cfg(unix)]
+impl Daemon<'_> {
+    fn handle(&mut self, request: &Request) -> Response {
+        match request {
+            Request::Reload => {
+                match reload(self.config_path, self.verbose, self.monitor, self.backend) {
+                    Ok((reloaded, report)) => {
+                        *self.clink = reloaded;
+                        publish_status(self.monitor, self.watching);
+                        serde_json::to_value(report)
+                            .map_or_else(|e| Response::error(e.to_string()), Response::ok)
                     }
+                    Err(e) => Response::error(e),
+                }
+            }
+            Request::Status => self.status(),
+            Request::Stats => serde_json::to_value(&self.monitor.statistics)
+                .map_or_else(|e| Response::error(e.to_string()), Response::ok),
+            Request::Pause { seconds } => {
+                // A second pause replaces the first one's timer.
+                let until = match *seconds {
+                    Some(seconds) => match pause_end(seconds) {
+                        Some(until) => Some(until),
+                        None => {
+                            return Response::error(format!(
+                                "A pause of {seconds} seconds ends too far in the future"
+                            ));
+                        }
+                    },
+                    None => None,
+                };
+                self.monitor.paused = true;
+                self.monitor.resume_at = until;
+                match until {
+                    Some(until) => log(
+                        self.verbose,
+                        &format!("Paused until {}", until.format(TIME_FORMAT)),
+                    ),
+                    None => log(self.verbose, "Paused"),
+                }
+                publish_status(self.monitor, self.watching);
+                self.status()
+            }
+            Request::Resume => {
+                if self.monitor.paused {
+                    self.monitor.resume();
+                    log(self.verbose, "Resumed");
+                    publish_status(self.monitor, self.watching);
+                }
+                self.status()
+            }
+            Request::CleanText { text } => serde_json::to_value(self.clink.find_and_replace(text))
+                .map_or_else(|e| Response::error(e.to_string()), Response::ok),
+            Request::Undo => match self.monitor.undo() {
+                Ok(rewrite) => {
+                    log(
+                        self.verbose,
+                        &format!("Restored the original {} selection", rewrite.selection),
+                    );
+                    let undone = UndoReport {
+                        selection: rewrite.selection,
+                        original: rewrite.original.text,
+                        cleaned: rewrite.cleaned.text,
+                        remaining: self.monitor.rewrites.len(),
+                    };
+                    serde_json::to_value(undone)
+                        .map_or_else(|e| Response::error(e.to_string()), Response::ok)
+                }
+                Err(e) => Response::error(e),
+            },
+            Request::Copy { text } => {
+                match self
+                    .monitor
+                    .put_back(Selection::Clipboard, &Flavors::text(text.clone()))
+                {
+                    Ok(()) => Response::ok(serde_json::Value::Null),
+                    Err(e) => Response::error(e),
                 }
             }
+            Request::Shutdown => Response::ok(serde_json::Value::Null),
         }
+    }
 
-        if let Ok(current_clipboard) = ctx.get_contents() {
-            if previous_clipboard != current_clipboard {
-                statistics.check_rollovers();
-                statistics.increment(0, 0, 0, 1);
-                let result = clink.find_and_replace(&current_clipboard);
-                let mut write_failed = false;
-                if result.text != current_clipboard {
-                    statistics.increment(
-                        result.urls_cleaned,
-                        result.params_removed,
-                        result.exits_unwrapped,
-                        0,
-                    );
-                    if let Err(e) = ctx.set_contents(result.text.clone()) {
-                        log_err(&format!("Failed to set clipboard: {e}"));
-                        write_failed = true;
-                    }
+    fn status(&self) -> Response {
+        serde_json::to_value(daemon_status(self.monitor, self.watching))
+            .map_or_else(|e| Response::error(e.to_string()), Response::ok)
+    }
+}
+
+// None when `seconds` from now can't be represented as a date.
+#[cfg(unix)]
+fn pause_end(seconds: u64) -> Option<chrono::DateTime<chrono::Local>> {
+    let delta = chrono::TimeDelta::try_seconds(seconds.try_into().ok()?)?;
+    chrono::Local::now().checked_add_signed(delta)
+}
+
+// The clipboard backend is opened once; everything else in the config is
+// swapped in place. A config that doesn't load leaves the running one, and
+// the `Clink` built from it, untouched.
+#[cfg(unix)]
+fn reload(
+    config_path: &Path,
+    verbose: bool,
+    monitor: &mut Monitor,
+    backend: &clipboard::ClipboardConfig,
+) -> Result<(Clink, ReloadReport), String> {
+    log(
+        verbose,
+        &format!("Reloading config from {}", config_path.display()),
+    );
+    match load_config(config_path) {
+        Ok(mut new_cfg) => {
+            new_cfg.verbose = verbose;
+            let mut warnings = resolve_and_record(&mut new_cfg);
+            monitor.set_selections(&new_cfg.selections);
+            monitor.sensitive = SensitivePatterns::new(&new_cfg.sensitive_patterns);
+            monitor.guards = CompiledGuards::new(&new_cfg.guards);
+            monitor.history = open_history(&new_cfg);
+            configure_notifier(&mut monitor.notifier, &new_cfg.notifications);
+            if new_cfg.clipboard != *backend {
+                let warning = format!(
+                    "Clipboard backend changed to {}, restart clink to switch",
+                    new_cfg.clipboard
+                );
+                log_err(&warning);
+                warnings.push(warning);
+            }
+            let report = ReloadReport::new(&new_cfg, warnings);
+            log(
+                verbose,
+                &format!(
+                    "Config reloaded: {} providers, {} rules, {} redirections",
+                    report.providers, report.rules, report.redirections
+                ),
+            );
+            Ok((Clink::new(new_cfg), report))
+        }
+        Err(e) => {
+            let message = format!("Failed to reload config: {e}");
+            log_err(&message);
+            Err(message)
+        }
+    }
+}
+
+fn daemon_status(monitor: &Monitor, watching: bool) -> runtime::DaemonStatus {
+    runtime::DaemonStatus {
+        pid: std::process::id(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        backend: monitor.clipboard.name(watching),
+        paused: monitor.paused,
+        paused_until: monitor
+            .resume_at
+            .map(|until| until.format(TIME_FORMAT).to_string()),
+        selections: monitor.selections.clone(),
+        dry_run: monitor.dry_run,
+    }
+}
+
+fn publish_status(monitor: &Monitor, watching: bool) {
+    if let Err(e) = runtime::write_status(&daemon_status(monitor, watching)) {
+        log_err(&format!("Failed to write status: {e}"));
+    }
+}
+
+// Blocks until the watcher reports a change or `timeout` passes. Returns
+// whether the clipboard should be read. A dead watcher switches the loop to
+// polling for the rest of the run.
+fn wait_for_change(
+    events: &Receiver<ClipboardEvent>,
+    watching: &mut bool,
+    monitor: &Monitor,
+    timeout: Duration,
+) -> bool {
+    let stopped = match events.recv_timeout(timeout) {
+        Ok(ClipboardEvent::Changed) => return true,
+        Err(RecvTimeoutError::Timeout) => return false,
+        Ok(ClipboardEvent::WatchStopped(reason)) => reason,
+        Err(RecvTimeoutError::Disconnected) => "watcher exited".to_string(),
+    };
+    log_err(&format!(
+        "Clipboard watcher stopped ({stopped}), falling back to polling"
+    ));
+    *watching = false;
+    publish_status(monitor, false);
+    true
+}
+
+// Which selections to read after a wakeup. With change notifications the
+// clipboard is only read when it changed; everything else is polled.
+fn due_selections(
+    selections: &[Selection],
+    watching: bool,
+    clipboard_changed: bool,
+) -> Vec<Selection> {
+    selections
+        .iter()
+        .copied()
+        .filter(|&s| !watching || s != Selection::Clipboard || clipboard_changed)
+        .collect()
+}
+
+// PRIMARY follows the mouse while text is being selected. Cleaning it
+// mid-drag would take the selection away from the user, so it's only
+// touched once two polls in a row read the same text.
+fn settled(settling: &mut Option<String>, current: &str) -> bool {
+    if settling.as_deref() == Some(current) {
+        return true;
+    }
+    *settling = Some(current.to_string());
+    false
+}
+
+// One line per link a rewrite would change. The text flavor is what gets
+// pasted; a change to the HTML or URI list alone is only mentioned.
+fn dry_run_diffs(current: &Flavors, cleaned: &Flavors, result: &CleanResult) -> Vec<String> {
+    if current.text == cleaned.text {
+        return vec!["only the rich-text flavors would change".to_string()];
+    }
+    if result.rewritten.is_empty() {
+        return vec![compact_diff(&current.text, &cleaned.text)];
+    }
+    result
+        .rewritten
+        .iter()
+        .map(|(original, rewritten)| compact_diff(original, rewritten))
+        .collect()
+}
+
+// What both sides share is written once and the difference in between as
+// [-removed-]{+added+}, e.g. `https://a.test/?[-utm_source=x&-]id=1`.
+fn compact_diff(original: &str, cleaned: &str) -> String {
+    let prefix: usize = original
+        .chars()
+        .zip(cleaned.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let (original_rest, cleaned_rest) = (&original[prefix..], &cleaned[prefix..]);
+    let suffix: usize = original_rest
+        .chars()
+        .rev()
+        .zip(cleaned_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let removed = &original_rest[..original_rest.len() - suffix];
+    let added = &cleaned_rest[..cleaned_rest.len() - suffix];
+    let mark = |open: &str, part: &str, close: &str| {
+        if part.is_empty() {
+            String::new()
+        } else {
+            format!("{open}{part}{close}")
+        }
+    };
+    format!(
+        "{}{}{}{}",
+        &original[..prefix],
+        mark("[-", removed, "-]"),
+        mark("{+", added, "+}"),
+        &original_rest[original_rest.len() - suffix..]
+    )
+}
+
+#[derive(Clone)]
+struct Rewrite {
+    selection: Selection,
+    original: Flavors,
+    cleaned: Flavors,
+}
+
+// The clipboard plus the state the loop keeps between checks. Each
+// selection has its own `previous`, so a link highlighted in PRIMARY doesn't
+// hide the same link copied to the clipboard, or the other way round.
+struct Monitor {
+    clipboard: Clipboard,
+    selections: Vec<Selection>,
+    previous: HashMap<Selection, String>,
+    // PRIMARY text seen on the last poll that hasn't been cleaned yet.
+    settling: Option<String>,
+    // Fingerprint of the sensitive content each selection holds, so it is
+    // recognized on later polls without being checked again.
+    skipped: HashMap<Selection, u64>,
+    sensitive: SensitivePatterns,
+    guards: CompiledGuards,
+    // Set over the control socket. The loop keeps running but leaves the
+    // clipboard alone, until `resume_at` when one is set. Only a restart
+    // forgets it.
+    paused: bool,
+    resume_at: Option<chrono::DateTime<chrono::Local>>,
+    // The latest rewrites, oldest first, for `clink undo`. Only ever in
+    // memory: the originals are exactly what clink is meant to get rid of.
+    rewrites: VecDeque<Rewrite>,
+    history: Option<History>,
+    notifier: Option<Notifier>,
+    // The rewrites notifications went out about, oldest first, by the token
+    // an Undo click on one comes back with.
+    notified: VecDeque<(u64, Rewrite)>,
+    // `clink run --dry-run`: rewrites are logged and counted, never written.
+    dry_run: bool,
+    // `clink run --verbose`: log lines also go to stdout.
+    verbose: bool,
+    statistics: stats::Stats,
+    stats_path: PathBuf,
+}
+
+impl Monitor {
+    fn new(clipboard: Clipboard, stats_path: PathBuf) -> Self {
+        let mut statistics = stats::load(&stats_path);
+        statistics.reset_session();
+        Self {
+            clipboard,
+            selections: Vec::new(),
+            previous: HashMap::new(),
+            settling: None,
+            skipped: HashMap::new(),
+            sensitive: SensitivePatterns::new(&[]),
+            guards: CompiledGuards::new(&Guards::default()),
+            paused: false,
+            resume_at: None,
+            rewrites: VecDeque::new(),
+            history: None,
+            notifier: None,
+            notified: VecDeque::new(),
+            dry_run: false,
+            verbose: false,
+            statistics,
+            stats_path,
+        }
+    }
 
-                    if let Err(e) = stats::save(&statistics, &stats_path) {
-                        log_err(&format!("Failed to save stats: {e}"));
+    fn set_selections(&mut self, configured: &[Selection]) {
+        let mut selections = Vec::new();
+        for &selection in configured {
+            if selections.contains(&selection) {
+                continue;
+            }
+            if self.clipboard.supports(selection) {
+                selections.push(selection);
+            } else {
+                log_err(&format!(
+                    "The {} clipboard backend can't access the {selection} selection, skipping it",
+                    self.clipboard.name(false)
+                ));
+            }
+        }
+        self.selections = selections;
+    }
+
+    // Secrets a password manager put on the clipboard, or anything matching
+    // `sensitive_patterns`, are left exactly as they are: not cleaned,
+    // counted or logged, so the manager's auto-clear keeps working.
+    //
+    // Text over `max_bytes` is neither hashed nor run through the patterns:
+    // only the password manager's mark is checked, and the size guard skips
+    // it right after.
+    fn skip_sensitive(&mut self, selection: Selection, text: &str) -> bool {
+        if self.guards.too_large(text) {
+            return self.clipboard.is_sensitive(selection);
+        }
+        let fingerprint = sensitive::fingerprint(text);
+        if self.skipped.get(&selection) == Some(&fingerprint) {
+            return true;
+        }
+        if self.clipboard.is_sensitive(selection) || self.sensitive.matches(text) {
+            self.skipped.insert(selection, fingerprint);
+            if selection == Selection::Primary {
+                self.settling = None;
+            }
+            return true;
+        }
+        self.skipped.remove(&selection);
+        false
+    }
+
+    fn pause_expired(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        self.paused && self.resume_at.is_some_and(|until| now >= until)
+    }
+
+    fn remember(&mut self, selection: Selection, original: Flavors, cleaned: Flavors) {
+        if self.rewrites.len() == UNDO_DEPTH {
+            self.rewrites.pop_front();
+        }
+        self.rewrites.push_back(Rewrite {
+            selection,
+            original,
+            cleaned,
+        });
+    }
+
+    // Puts the original of the latest rewrite back. It becomes `previous`,
+    // so the next check sees nothing new and leaves it alone until
+    // something else is copied.
+    fn undo(&mut self) -> Result<Rewrite, String> {
+        let rewrite = self
+            .rewrites
+            .pop_back()
+            .ok_or("Nothing to undo: clink remembers no earlier rewrites")?;
+        if let Err(e) = self.put_back(rewrite.selection, &rewrite.original) {
+            self.rewrites.push_back(rewrite);
+            return Err(e);
+        }
+        Ok(rewrite)
+    }
+
+    fn notify(&mut self, result: &CleanResult) {
+        let Some(notifier) = &mut self.notifier else {
+            return;
+        };
+        let token = notifier.notify(result);
+        let Some(rewrite) = self.rewrites.back().cloned() else {
+            return;
+        };
+        if self.notified.len() == UNDO_DEPTH {
+            self.notified.pop_front();
+        }
+        self.notified.push_back((token, rewrite));
+    }
+
+    fn handle_notifier_events(&mut self, verbose: bool) {
+        let Some(notifier) = &mut self.notifier else {
+            return;
+        };
+        for event in notifier.events() {
+            match event {
+                Event::Undo(token) => match self.undo_notified(token) {
+                    Ok(selection) => log(
+                        verbose,
+                        &format!(
+                            "Restored the original {selection} selection from the notification"
+                        ),
+                    ),
+                    Err(e) => log_err(&e),
+                },
+                Event::Failed(e) => log_err(&e),
+            }
+        }
+    }
+
+    // Reverts the rewrite the notification was about, but only while the
+    // selection still holds what clink wrote: whatever was copied since is
+    // what the user wants now.
+    fn undo_notified(&mut self, token: u64) -> Result<Selection, String> {
+        let (_, rewrite) = self
+            .notified
+            .iter()
+            .position(|(t, _)| *t == token)
+            .and_then(|i| self.notified.remove(i))
+            .ok_or("Nothing to undo from the notification")?;
+        let selection = rewrite.selection;
+        if self.clipboard.get_text(selection)? != rewrite.cleaned.text {
+            return Err(format!(
+                "The {selection} selection changed since the notification, left it as it is"
+            ));
+        }
+        self.put_back(selection, &rewrite.original)?;
+        if let Some(i) = self
+            .rewrites
+            .iter()
+            .rposition(|r| r.selection == selection && r.cleaned == rewrite.cleaned)
+        {
+            self.rewrites.remove(i);
+        }
+        Ok(selection)
+    }
+
+    // Writes `flavors` to the selection as they are: they become `previous`,
+    // so the loop doesn't clean them on its next check.
+    fn put_back(&mut self, selection: Selection, flavors: &Flavors) -> Result<(), String> {
+        self.clipboard
+            .set_flavors(selection, flavors)
+            .map_err(|e| format!("Failed to set {selection} selection: {e}"))?;
+        self.previous.insert(selection, flavors.text.clone());
+        if selection == Selection::Primary {
+            self.settling = None;
+        }
+        Ok(())
+    }
+
+    // Whatever was copied while paused was meant to be left alone, so it
+    // becomes the baseline instead of being cleaned on the next check.
+    fn resume(&mut self) {
+        self.paused = false;
+        self.resume_at = None;
+        self.settling = None;
+        for &selection in &self.selections {
+            if let Ok(text) = self.clipboard.get_text(selection) {
+                self.previous.insert(selection, text);
+            }
+        }
+    }
+
+    fn check(&mut self, clink: &Clink, selection: Selection) {
+        let Ok(current_text) = self.clipboard.get_text(selection) else {
+            return;
+        };
+        let previous = self.previous.entry(selection).or_default();
+        if *previous == current_text || self.skip_sensitive(selection, &current_text) {
+            return;
+        }
+        if selection == Selection::Primary && !settled(&mut self.settling, &current_text) {
+            return;
+        }
+        self.statistics.check_rollovers();
+        self.statistics.increment(0, 0, 0, 1);
+        // Guards look at the plain text only; rich flavors are read after
+        // they pass.
+        if let Some(skip) = self.guards.check(&current_text) {
+            self.statistics.increment_skip(skip);
+            if let Err(e) = stats::save(&self.statistics, &self.stats_path) {
+                log_err(&format!("Failed to save stats: {e}"));
+            }
+            self.previous.insert(selection, current_text);
+            return;
+        }
+        let current = self.clipboard.get_flavors(selection, current_text);
+        let (cleaned, result) = flavors::clean(clink, &current);
+        for w in &result.warnings {
+            log_err(&format!("warning: {w}"));
+        }
+        let mut write_failed = false;
+        if cleaned != current {
+            self.statistics.increment(
+                result.urls_cleaned,
+                result.params_removed,
+                result.exits_unwrapped,
+                0,
+            );
+            if selection == Selection::Primary {
+                self.statistics.increment_primary(result.urls_cleaned);
+            }
+            if self.dry_run {
+                for diff in dry_run_diffs(&current, &cleaned, &result) {
+                    log(self.verbose, &format!("Dry run, {selection}: {diff}"));
+                }
+                if let Err(e) = stats::save(&self.statistics, &self.stats_path) {
+                    log_err(&format!("Failed to save stats: {e}"));
+                }
+                // What was copied stays on the clipboard, so that is what
+                // the next check has to recognize.
+                self.previous.insert(selection, current.text);
+                return;
+            }
+            if let Err(e) = self.clipboard.set_flavors(selection, &cleaned) {
+                log_err(&format!("Failed to set {selection} selection: {e}"));
+                write_failed = true;
+            } else {
+                if let Some(history) = &mut self.history {
+                    if let Err(e) = history.record(selection, &current.text, &result) {
+                        log_err(&format!("Failed to record history: {e}"));
                     }
                 }
-                previous_clipboard =
-                    advance_previous(result.text, previous_clipboard, write_failed);
+                self.remember(selection, current, cleaned.clone());
+                if result.urls_cleaned > 0 {
+                    self.notify(&result);
+                }
+            }
+
+            if let Err(e) = stats::save(&self.statistics, &self.stats_path) {
+                log_err(&format!("Failed to save stats: {e}"));
             }
         }
-        thread::sleep(sleep_duration);
+        let previous = self.previous.entry(selection).or_default();
+        *previous = advance_previous(cleaned.text, std::mem::take(previous), write_failed);
     }
 }
 
@@ -212,4 +900,363 @@ mod tests {
         );
         assert_eq!(next, "https://test.test/?fbclid=x");
     }
+
+    #[test]
+    fn watched_clipboard_is_read_only_on_change() {
+        let both = [Selection::Clipboard, Selection::Primary];
+        assert_eq!(due_selections(&both, true, false), [Selection::Primary]);
+        assert_eq!(due_selections(&both, true, true), both);
+        assert_eq!(due_selections(&both, false, false), both);
+        assert_eq!(
+            due_selections(&[Selection::Primary], true, true),
+            [Selection::Primary]
+        );
+    }
+
+    #[test]
+    fn primary_waits_for_the_selection_to_settle() {
+        let mut settling = None;
+        assert!(!settled(&mut settling, "https://a.test/?utm_"));
+        assert!(!settled(&mut settling, "https://a.test/?utm_source=x"));
+        assert!(settled(&mut settling, "https://a.test/?utm_source=x"));
+    }
+
+    // A monitor over a regular file in a fresh temp dir, plus a clink that
+    // strips `utm_source`.
+    fn file_monitor(name: &str) -> (Monitor, Clink, PathBuf) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (clipboard, _) =
+            clipboard::open(&clipboard::ClipboardConfig::File(clipboard::FileConfig {
+                path: dir.join("clipboard"),
+                output: None,
+            }))
+            .unwrap();
+        let monitor = Monitor::new(clipboard, dir.join("stats.toml"));
+
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            crate::provider::ProviderConfig {
+                rules: vec!["utm_source".into()],
+                ..Default::default()
+            },
+        );
+        let clink = Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        });
+        (monitor, clink, dir)
+    }
+
+    #[test]
+    fn cleans_a_file_backed_clipboard_headlessly() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_monitor");
+        let path = dir.join("clipboard");
+        std::fs::write(&path, "see https://a.test/?utm_source=x").unwrap();
+        monitor.set_selections(&[Selection::Clipboard, Selection::Primary]);
+        assert_eq!(monitor.selections, [Selection::Clipboard]);
+
+        monitor.check(&clink, Selection::Clipboard);
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "see https://a.test/"
+        );
+        let session = &monitor.statistics.session.counters;
+        assert_eq!(session.urls_cleaned, 1);
+        assert_eq!(session.clipboard_checks, 1);
+        assert!(dir.join("stats.toml").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn undo_restores_the_original_without_recleaning_it() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_undo");
+        let path = dir.join("clipboard");
+        assert!(monitor.undo().is_err());
+        for i in 0..=UNDO_DEPTH {
+            std::fs::write(&path, format!("https://a.test/{i}?utm_source=x")).unwrap();
+            monitor.check(&clink, Selection::Clipboard);
+        }
+        assert_eq!(monitor.rewrites.len(), UNDO_DEPTH);
+
+        let rewrite = monitor.undo().unwrap();
+        assert_eq!(rewrite.cleaned.text, format!("https://a.test/{UNDO_DEPTH}"));
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("https://a.test/{UNDO_DEPTH}?utm_source=x")
+        );
+        assert_eq!(monitor.rewrites.len(), UNDO_DEPTH - 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compact_diff_marks_only_what_changes() {
+        assert_eq!(
+            compact_diff("https://a.test/?utm_source=x&id=1", "https://a.test/?id=1"),
+            "https://a.test/?[-utm_source=x&-]id=1"
+        );
+        assert_eq!(
+            compact_diff(
+                "https://l.test/?u=https%3A%2F%2Fb.test%2F",
+                "https://b.test/"
+            ),
+            "https://[-l.test/?u=https%3A%2F%2Fb.test%2F-]{+b.test/+}"
+        );
+        assert_eq!(
+            compact_diff("https://ä.test/?x=ö", "https://ä.test/?x=ü"),
+            "https://ä.test/?x=[-ö-]{+ü+}"
+        );
+    }
+
+    #[test]
+    fn dry_run_counts_but_leaves_the_clipboard_alone() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_dry_run");
+        let path = dir.join("clipboard");
+        monitor.dry_run = true;
+        std::fs::write(&path, "see https://a.test/?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "see https://a.test/?utm_source=x"
+        );
+        let session = &monitor.statistics.session.counters;
+        assert_eq!(session.urls_cleaned, 1);
+        assert_eq!(session.clipboard_checks, 1);
+        assert!(monitor.rewrites.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dry_run_diffs_show_each_rewritten_link() {
+        let clink = file_monitor("clink_test_headless_dry_run_diffs").1;
+        let current = Flavors::text(
+            "https://a.test/?utm_source=x and https://b.test/ and https://c.test/?id=1&utm_source=y"
+                .to_string(),
+        );
+        let (cleaned, result) = flavors::clean(&clink, &current);
+        assert_eq!(
+            dry_run_diffs(&current, &cleaned, &result),
+            [
+                "https://a.test/[-?utm_source=x-]",
+                "https://c.test/?id=1[-&utm_source=y-]"
+            ]
+        );
+
+        let html = Flavors {
+            html: Some(r#"<a href="https://a.test/?utm_source=x">a</a>"#.to_string()),
+            ..Flavors::text("a".to_string())
+        };
+        let (cleaned, result) = flavors::clean(&clink, &html);
+        assert_eq!(
+            dry_run_diffs(&html, &cleaned, &result),
+            ["only the rich-text flavors would change"]
+        );
+    }
+
+    #[test]
+    fn notification_undo_only_reverts_what_is_still_there() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_notified");
+        let path = dir.join("clipboard");
+        std::fs::write(&path, "https://a.test/1?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        // What `notify` keeps when the notification goes out.
+        let notified = |monitor: &mut Monitor, token| {
+            let rewrite = monitor.rewrites.back().cloned().unwrap();
+            monitor.notified.push_back((token, rewrite));
+        };
+        notified(&mut monitor, 1);
+        std::fs::write(&path, "https://a.test/2?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        notified(&mut monitor, 2);
+        assert!(monitor.undo_notified(1).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "https://a.test/2");
+
+        assert_eq!(monitor.undo_notified(2), Ok(Selection::Clipboard));
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "https://a.test/2?utm_source=x"
+        );
+        assert_eq!(monitor.rewrites.len(), 1);
+        assert!(monitor.undo_notified(2).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rewrites_are_recorded_in_the_history() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_history");
+        let path = dir.join("clipboard");
+        let history_path = dir.join("history.jsonl");
+        monitor.history = History::open(history_path.clone(), &ClinkConfig::default());
+        std::fs::write(&path, "see https://a.test/?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        std::fs::write(&path, "nothing to clean").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+
+        let entries = crate::history::load(&history_path);
+        assert_eq!(entries.len(), 1);
+        // The tracking param never reaches the disk.
+        assert_eq!(entries[0].original, "https://a.test/");
+        assert_eq!(entries[0].cleaned, "https://a.test/");
+        assert_eq!(entries[0].params_removed, 1);
+
+        // Copying an original back from the history doesn't get it cleaned,
+        // even when `history_redact = 'none'` kept it whole.
+        monitor
+            .put_back(
+                Selection::Clipboard,
+                &Flavors::text("https://a.test/?utm_source=x".to_string()),
+            )
+            .unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "https://a.test/?utm_source=x"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn content_copied_while_paused_is_kept_after_resume() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_pause");
+        let path = dir.join("clipboard");
+        monitor.set_selections(&[Selection::Clipboard]);
+        monitor.paused = true;
+        let now = chrono::Local::now();
+        monitor.resume_at = Some(now + chrono::Duration::minutes(10));
+        assert!(!monitor.pause_expired(now));
+        assert!(monitor.pause_expired(now + chrono::Duration::minutes(10)));
+        std::fs::write(&path, "https://a.test/?utm_source=x").unwrap();
+        monitor.resume();
+        assert!(!monitor.paused);
+        assert!(!monitor.pause_expired(now + chrono::Duration::minutes(10)));
+
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "https://a.test/?utm_source=x"
+        );
+        std::fs::write(&path, "https://b.test/?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "https://b.test/");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn control_requests_read_daemon_state() {
+        let (mut monitor, mut clink, dir) = file_monitor("clink_test_headless_control");
+        monitor.set_selections(&[Selection::Clipboard]);
+        let backend = clipboard::ClipboardConfig::default();
+        let mut daemon = Daemon {
+            config_path: &dir.join("config.toml"),
+            verbose: false,
+            backend: &backend,
+            monitor: &mut monitor,
+            clink: &mut clink,
+            watching: false,
+        };
+
+        let cleaned = daemon
+            .handle(&Request::CleanText {
+                text: "https://a.test/?utm_source=x".into(),
+            })
+            .into_result()
+            .unwrap();
+        assert_eq!(cleaned["text"], "https://a.test/");
+        assert_eq!(cleaned["urls_cleaned"], 1);
+
+        let status: runtime::DaemonStatus =
+            serde_json::from_value(daemon.handle(&Request::Status).into_result().unwrap()).unwrap();
+        assert_eq!(status.pid, std::process::id());
+        assert_eq!(status.selections, [Selection::Clipboard]);
+        assert!(!status.paused);
+
+        // A pause that would end past what a date can hold is refused.
+        for seconds in [u64::MAX, i64::MAX as u64, 100_000_000 * 24 * 60 * 60] {
+            let refused = daemon.handle(&Request::Pause {
+                seconds: Some(seconds),
+            });
+            assert!(refused.into_result().is_err(), "{seconds}");
+        }
+        assert!(!daemon.monitor.paused);
+
+        // A broken config is reported back and leaves the old rules active.
+        std::fs::write(dir.join("config.toml"), "mode = 42").unwrap();
+        assert!(daemon.handle(&Request::Reload).into_result().is_err());
+        assert_eq!(
+            clink.find_and_replace("https://a.test/?utm_source=x").text,
+            "https://a.test/"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sensitive_content_is_left_alone_and_uncounted() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_sensitive");
+        let path = dir.join("clipboard");
+        let secret = "token=https://a.test/?utm_source=x";
+        std::fs::write(&path, secret).unwrap();
+        monitor.sensitive = SensitivePatterns::new(&["^token=".to_string()]);
+
+        monitor.check(&clink, Selection::Clipboard);
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), secret);
+        assert_eq!(monitor.statistics.session.counters.clipboard_checks, 0);
+        assert!(!dir.join("stats.toml").exists());
+
+        // The next copy is cleaned as usual.
+        std::fs::write(&path, "https://a.test/?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "https://a.test/");
+
+        // Past `max_bytes` the patterns never see the text; the size guard
+        // skips it.
+        monitor.guards = CompiledGuards::new(&Guards {
+            max_bytes: 16,
+            ..Guards::default()
+        });
+        std::fs::write(&path, secret).unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), secret);
+        assert_eq!(monitor.statistics.session.counters.skipped_too_large, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn guarded_content_is_counted_and_left_alone() {
+        let (mut monitor, clink, dir) = file_monitor("clink_test_headless_guards");
+        let path = dir.join("clipboard");
+        monitor.guards = CompiledGuards::new(&Guards {
+            only_when_clipboard_is_single_url: true,
+            skip_patterns: vec![r"^\s*\{".to_string()],
+            ..Guards::default()
+        });
+
+        let json = r#"{"url": "https://a.test/?utm_source=x"}"#;
+        std::fs::write(&path, json).unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), json);
+
+        std::fs::write(&path, "see https://a.test/?utm_source=x").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+
+        std::fs::write(&path, "https://a.test/?utm_source=x\n").unwrap();
+        monitor.check(&clink, Selection::Clipboard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "https://a.test/\n");
+
+        let session = &monitor.statistics.session.counters;
+        assert_eq!(session.skipped_by_pattern, 1);
+        assert_eq!(session.skipped_not_single_url, 1);
+        assert_eq!(session.urls_cleaned, 1);
+        assert_eq!(session.clipboard_checks, 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -20,7 +20,56 @@ fn format_stats_table(stats: &stats::Stats, is_running: bool) -> String {
         format_row("This year", &stats.year.counters),
         format_row("Total", &stats.total),
     ];
-    format!("{header}\n{}", rows.join("\n"))
+    let mut lines = vec![header];
+    lines.extend(rows);
+    // The extra lines only show up once they have something to say, so
+    // setups that don't use PRIMARY or guards see the same table as before.
+    if stats.total.primary_urls_cleaned > 0 {
+        lines.push(format!(
+            "URLs cleaned from the PRIMARY selection: {} since restart, {} total",
+            session.primary_urls_cleaned, stats.total.primary_urls_cleaned
+        ));
+    }
+    let total = &stats.total;
+    if total.skipped_too_large + total.skipped_by_pattern + total.skipped_not_single_url > 0 {
+        lines.push(format!(
+            "Skipped by guards (total): {} over max_bytes, {} by skip_patterns, {} not a single URL",
+            total.skipped_too_large, total.skipped_by_pattern, total.skipped_not_single_url
+        ));
+    }
+    lines.join("\n")
+}
+
+fn paused_line(status: &runtime::DaemonStatus) -> Option<String> {
+    if !status.paused {
+        return None;
+    }
+    Some(match &status.paused_until {
+        Some(until) => format!("Paused until {until} (`clink resume` to end it now)"),
+        None => "Paused until `clink resume`".to_string(),
+    })
+}
+
+// The daemon only writes the stats file when something was cleaned or
+// skipped, so its own counters are fresher than the file. Without a control
+// socket, the status and stats files are all there is.
+#[cfg(unix)]
+fn query_daemon() -> (Option<runtime::DaemonStatus>, Option<stats::Stats>) {
+    use crate::control::{Client, Request};
+
+    fn ask<T: serde::de::DeserializeOwned>(request: &Request) -> Option<T> {
+        let data = Client::connect(&runtime::socket_path())
+            .and_then(|client| client.send(request))
+            .and_then(crate::control::Response::into_result)
+            .ok()?;
+        serde_json::from_value(data).ok()
+    }
+    (ask(&Request::Status), ask(&Request::Stats))
+}
+
+#[cfg(not(unix))]
+fn query_daemon() -> (Option<runtime::DaemonStatus>, Option<stats::Stats>) {
+    (None, None)
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -28,13 +77,26 @@ pub fn execute() -> Result<(), String> {
     let pid = runtime::read_pid();
     let is_running = pid.is_some_and(runtime::is_running);
 
+    let mut live_stats = None;
     match pid {
         Some(pid) if runtime::is_running(pid) => {
             println!("clink is running (PID {pid})");
+            let (status, counters) = query_daemon();
+            live_stats = counters;
+            if let Some(status) = status.or_else(runtime::read_status) {
+                println!("Clipboard backend: {}", status.backend);
+                if let Some(line) = paused_line(&status) {
+                    println!("{line}");
+                }
+                if status.dry_run {
+                    println!("Dry run: changes are only logged, the clipboard is left alone");
+                }
+            }
         }
         Some(pid) => {
             println!("clink is not running (stale PID file for PID {pid})");
             runtime::remove_pid_file();
+            runtime::remove_status();
         }
         None => {
             println!("clink is not running");
@@ -42,7 +104,7 @@ pub fn execute() -> Result<(), String> {
     }
 
     let stats_path = runtime::stats_file_path();
-    let stats = stats::load(&stats_path);
+    let stats = live_stats.unwrap_or_else(|| stats::load(&stats_path));
     println!("\nStatistics:\n{}", format_stats_table(&stats, is_running));
 
     let log_path = runtime::log_file_path();
@@ -65,6 +127,20 @@ pub fn execute() -> Result<(), String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn paused_line_shows_the_timer() {
+        let mut status = runtime::DaemonStatus::default();
+        assert_eq!(paused_line(&status), None);
+        status.paused = true;
+        assert_eq!(paused_line(&status).unwrap(), "Paused until `clink resume`");
+        status.paused_until = Some("2026-04-09 10:10:00".to_string());
+        assert!(
+            paused_line(&status)
+                .unwrap()
+                .starts_with("Paused until 2026-04-09 10:10:00")
+        );
+    }
+
     #[test]
     fn format_stats_table_output() {
         let stats = stats::Stats {
@@ -75,6 +151,7 @@ mod tests {
                     params_removed: 12,
                     exits_unwrapped: 1,
                     clipboard_checks: 847,
+                    ..Default::default()
                 },
             },
             today: stats::DayBucket {
@@ -84,6 +161,7 @@ mod tests {
                     params_removed: 20,
                     exits_unwrapped: 2,
                     clipboard_checks: 1200,
+                    ..Default::default()
                 },
             },
             month: stats::MonthBucket {
@@ -93,6 +171,7 @@ mod tests {
                     params_removed: 180,
                     exits_unwrapped: 10,
                     clipboard_checks: 28000,
+                    ..Default::default()
                 },
             },
             year: stats::YearBucket {
@@ -101,14 +180,16 @@ mod tests {
                     urls_cleaned: 200,
                     params_removed: 800,
                     exits_unwrapped: 40,
-                    clipboard_checks: 100000,
+                    clipboard_checks: 100_000,
+                    ..Default::default()
                 },
             },
             total: stats::Counters {
                 urls_cleaned: 500,
                 params_removed: 2000,
                 exits_unwrapped: 100,
-                clipboard_checks: 300000,
+                clipboard_checks: 300_000,
+                ..Default::default()
             },
         };
 
@@ -122,6 +203,28 @@ mod tests {
         assert!(output.contains("300000"));
     }
 
+    #[test]
+    fn format_stats_table_shows_primary_share_once_used() {
+        let mut stats = stats::Stats::default();
+        stats.increment(3, 3, 0, 5);
+        assert!(!format_stats_table(&stats, true).contains("PRIMARY"));
+        stats.increment_primary(2);
+        assert!(
+            format_stats_table(&stats, true)
+                .ends_with("URLs cleaned from the PRIMARY selection: 2 since restart, 2 total")
+        );
+    }
+
+    #[test]
+    fn format_stats_table_shows_guard_skips_once_used() {
+        let mut stats = stats::Stats::default();
+        assert!(!format_stats_table(&stats, true).contains("guards"));
+        stats.increment_skip(crate::guard::Skip::Pattern);
+        assert!(format_stats_table(&stats, true).ends_with(
+            "Skipped by guards (total): 0 over max_bytes, 1 by skip_patterns, 0 not a single URL"
+        ));
+    }
+
     #[test]
     fn format_stats_table_not_running_zeros_session() {
         let mut stats = stats::Stats::default();
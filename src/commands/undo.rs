@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+use url::Url;
+
+use crate::clink::Clink;
+use crate::provider::GLOBAL_PROVIDER;
+
+pub fn execute(config_path: &Path, exclude: bool) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use crate::control::{self, Request, UndoReport};
+
+        let data = control::request(&Request::Undo)?;
+        let undone: UndoReport = serde_json::from_value(data)
+            .map_err(|e| format!("Invalid undo report from clink: {e}"))?;
+        println!(
+            "Restored the original {} selection ({} older rewrites left to undo).",
+            undone.selection, undone.remaining
+        );
+        if exclude {
+            exclude_hosts(config_path, &undone.original)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (config_path, exclude);
+        Err("Undo is not supported on this platform.".to_string())
+    }
+}
+
+// Adds the exceptions to the config file, keeping its comments and layout,
+// and has the daemon pick them up.
+#[cfg(unix)]
+fn exclude_hosts(config_path: &Path, original: &str) -> Result<(), String> {
+    let mut cfg = crate::config::load_config(config_path)?;
+    let _ = crate::remote::resolve_patterns(&mut cfg, &crate::runtime::data_dir());
+    let priorities: HashMap<String, i32> = cfg
+        .providers
+        .iter()
+        .map(|(name, provider)| (name.clone(), provider.effective_priority()))
+        .collect();
+    let wanted = exclusions(&Clink::new(cfg), original);
+
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| format!("Failed to read {}: {e}", config_path.display()))?;
+    let (updated, added, created) = add_exceptions(&content, &wanted, &priorities)?;
+    if added.is_empty() {
+        println!("No new exceptions to add.");
+        return Ok(());
+    }
+    crate::runtime::write_atomic(config_path, &updated)?;
+    for provider in &created {
+        match priorities.get(provider) {
+            Some(priority) if provider != GLOBAL_PROVIDER => println!(
+                "Created [providers.{provider}] with priority = {priority}, the priority it had before."
+            ),
+            _ => println!("Created [providers.{provider}]."),
+        }
+    }
+    for (provider, exception) in &added {
+        println!("Added exception '{exception}' to [providers.{provider}].");
+    }
+    crate::control::request(&crate::control::Request::Reload)?;
+    println!("Reloaded clink.");
+    Ok(())
+}
+
+// A provider name and an exception for it.
+type Exception = (String, String);
+
+// For every link the rewrite changed: an exception for its host on each
+// provider that acted on it. A redirect is unwrapped by one provider, so
+// only that one needs it; otherwise it's every provider that matched. The
+// global provider applies to every URL and always gets one.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn exclusions(clink: &Clink, original: &str) -> Vec<Exception> {
+    let mut wanted: Vec<Exception> = Vec::new();
+    for report in clink.explain(original) {
+        if report.cleaned == report.original {
+            continue;
+        }
+        let Some(host) = Url::parse(&report.original)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+        else {
+            continue;
+        };
+        let exception = format!("^https?://{}(?:[:/?#]|$)", regex::escape(&host));
+        let mut providers = vec![GLOBAL_PROVIDER.to_string()];
+        match report.redirects.first() {
+            Some(hop) => providers.push(hop.provider.clone()),
+            None => providers.extend(
+                report
+                    .providers
+                    .into_iter()
+                    .filter(|p| p.excluded_by.is_none())
+                    .map(|p| p.provider),
+            ),
+        }
+        for provider in providers {
+            let entry = (provider, exception.clone());
+            if !wanted.contains(&entry) {
+                wanted.push(entry);
+            }
+        }
+    }
+    wanted
+}
+
+// Returns the edited document, the exceptions that weren't there yet and
+// the provider tables that had to be created for them. A provider that only
+// comes from remote or built-in patterns counts as local once it has a
+// table, so a created table pins the priority the provider had.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn add_exceptions(
+    content: &str,
+    wanted: &[Exception],
+    priorities: &HashMap<String, i32>,
+) -> Result<(String, Vec<Exception>, Vec<String>), String> {
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Failed to parse config: {e}"))?;
+    let providers = doc
+        .entry("providers")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or("`providers` in the config is not a table")?;
+    let mut added = Vec::new();
+    let mut created = Vec::new();
+    for (name, exception) in wanted {
+        if !providers.contains_key(name) {
+            let mut table = Table::new();
+            if let Some(&priority) = priorities.get(name).filter(|_| name != GLOBAL_PROVIDER) {
+                table.insert("priority", toml_edit::value(i64::from(priority)));
+            }
+            providers.insert(name, Item::Table(table));
+            created.push(name.clone());
+        }
+        let provider = providers
+            .get_mut(name)
+            .expect("inserted above")
+            .as_table_like_mut()
+            .ok_or_else(|| format!("`providers.{name}` in the config is not a table"))?;
+        let exceptions = provider
+            .entry("exceptions")
+            .or_insert(Item::Value(Value::Array(Array::new())))
+            .as_array_mut()
+            .ok_or_else(|| format!("`providers.{name}.exceptions` in the config is not a list"))?;
+        if exceptions
+            .iter()
+            .any(|e| e.as_str() == Some(exception.as_str()))
+        {
+            continue;
+        }
+        exceptions.push(exception.as_str());
+        added.push((name.clone(), exception.clone()));
+    }
+    Ok((doc.to_string(), added, created))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClinkConfig;
+    use crate::provider::ProviderConfig;
+    use std::collections::HashMap;
+
+    fn clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            GLOBAL_PROVIDER.to_string(),
+            ProviderConfig {
+                rules: vec!["utm_source".into()],
+                ..Default::default()
+            },
+        );
+        providers.insert(
+            "shop".to_string(),
+            ProviderConfig {
+                url_pattern: Some(r"^https?://shop\.test".into()),
+                rules: vec!["ref".into()],
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn exclusions_cover_every_provider_on_a_rewritten_link() {
+        let wanted = exclusions(
+            &clink(),
+            "https://shop.test/item?ref=x and https://clean.test/ and https://a.test/?utm_source=y",
+        );
+        let shop = r"^https?://shop\.test(?:[:/?#]|$)".to_string();
+        let a = r"^https?://a\.test(?:[:/?#]|$)".to_string();
+        assert_eq!(
+            wanted,
+            [
+                ("global".to_string(), shop.clone()),
+                ("shop".to_string(), shop),
+                ("global".to_string(), a),
+            ]
+        );
+    }
+
+    #[test]
+    fn add_exceptions_keeps_comments_and_skips_duplicates() {
+        let content = "\
+# my config
+mode = \"remove\"
+replace_to = \"clink\"
+sleep_duration = 150
+
+[providers.global]
+# tracked everywhere
+rules = [\"utm_source\"]
+exceptions = [\"^https?://old\\\\.test/\"]
+";
+        let wanted = vec![
+            (
+                "global".to_string(),
+                r"^https?://a\.test(?:[:/?#]|$)".to_string(),
+            ),
+            ("global".to_string(), r"^https?://old\.test/".to_string()),
+            (
+                "shop".to_string(),
+                r"^https?://a\.test(?:[:/?#]|$)".to_string(),
+            ),
+        ];
+        let priorities = HashMap::from([("shop".to_string(), 50)]);
+        let (updated, added, created) = add_exceptions(content, &wanted, &priorities).unwrap();
+        assert_eq!(added, [wanted[0].clone(), wanted[2].clone()]);
+        assert_eq!(created, ["shop"]);
+        assert!(updated.contains("# my config"));
+        assert!(updated.contains("# tracked everywhere"));
+
+        let cfg: ClinkConfig = toml::from_str(&updated).unwrap();
+        assert_eq!(
+            cfg.providers["global"].exceptions,
+            [r"^https?://old\.test/", r"^https?://a\.test(?:[:/?#]|$)"]
+        );
+        assert_eq!(
+            cfg.providers["shop"].exceptions,
+            [r"^https?://a\.test(?:[:/?#]|$)"]
+        );
+        // The stub keeps the priority the remote provider had.
+        assert_eq!(cfg.providers["shop"].priority, Some(50));
+        assert_eq!(cfg.providers["global"].priority, None);
+
+        let (again, added, created) = add_exceptions(&updated, &wanted, &priorities).unwrap();
+        assert!(added.is_empty());
+        assert!(created.is_empty());
+        assert_eq!(again, updated);
+    }
+
+    #[test]
+    fn add_exceptions_rejects_a_malformed_providers_key() {
+        assert!(
+            add_exceptions(
+                "providers = 1",
+                &[("g".into(), "x".into())],
+                &HashMap::new()
+            )
+            .is_err()
+        );
+    }
+}
@@ -139,10 +139,10 @@ fn translate_clearurls(body: &str) -> Result<RemotePatterns, String> {
         result.providers.len(),
         result.rules_translated
     );
-    if result.complete_providers_skipped > 0 {
+    if result.complete_providers > 0 {
         println!(
-            "Skipped {} ClearURLs `completeProvider` entries (block-the-whole-site rules — clink only strips params, not full domains)",
-            result.complete_providers_skipped
+            "Included {} ClearURLs `completeProvider` entries (ad/tracking domains — handled per `complete_provider_action`)",
+            result.complete_providers
         );
     }
     println!(
@@ -301,14 +301,14 @@ mod tests {
 
     #[test]
     fn test_parse_clink_toml_valid() {
-        let toml = r#"
+        let toml = r"
 [providers.global]
 rules = ['fbclid', 'gclid']
 
 [providers.exitsc]
 url_pattern = '^https?://exit\.sc'
 redirections = ['^https?://exit\.sc/\?.*?url=([^&]+)']
-"#;
+";
         let result = parse_clink_toml(toml).unwrap();
         assert_eq!(result.providers.len(), 2);
         assert_eq!(result.providers["global"].rules.len(), 2);
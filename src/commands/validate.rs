@@ -1,5 +1,5 @@
-use crate::config::load_config;
-use crate::provider::check_provider;
+use crate::config::{describe_history, describe_selections, load_config};
+use crate::provider::{check_provider, check_redirect_overlaps};
 use crate::remote::resolve_patterns;
 use crate::runtime;
 use std::path::Path;
@@ -19,16 +19,54 @@ pub fn execute(config_path: &Path) -> Result<(), String> {
     for (name, p) in &cfg.providers {
         warnings.extend(check_provider(name, p));
     }
+    warnings.extend(check_redirect_overlaps(&cfg.providers));
 
     let rule_count: usize = cfg.providers.values().map(|p| p.rules.len()).sum();
     let redirect_count: usize = cfg.providers.values().map(|p| p.redirections.len()).sum();
+    let complete_count = cfg
+        .providers
+        .values()
+        .filter(|p| p.complete_provider == Some(true))
+        .count();
 
     println!("Config at {}:", config_path.display());
     println!("  Mode: {}", cfg.mode);
     println!("  Sleep duration: {}ms", cfg.sleep_duration);
+    println!("  Selections: {}", describe_selections(&cfg.selections));
+    println!("  Clipboard backend: {}", cfg.clipboard);
+    println!("  Guards: {}", cfg.guards);
+    println!("  History: {}", describe_history(&cfg));
+    println!("  Notifications: {}", cfg.notifications);
     println!("  Providers: {}", cfg.providers.len());
     println!("  Total rules: {rule_count}");
     println!("  Total redirections: {redirect_count}");
+    println!(
+        "  Complete providers: {complete_count} (action: {})",
+        cfg.complete_provider_action
+    );
+    if let Some(canonicalize) = &cfg.canonicalize {
+        println!("  Canonicalize: {canonicalize}");
+    }
+
+    let mut unrules: Vec<String> = cfg
+        .providers
+        .iter()
+        .flat_map(|(name, p)| p.unrules.iter().map(move |u| format!("{name}:{u}")))
+        .collect();
+    if !unrules.is_empty() {
+        unrules.sort();
+        println!("  Unrules: {}", unrules.join(", "));
+    }
+
+    let mut overrides: Vec<String> = cfg
+        .providers
+        .iter()
+        .filter_map(|(name, p)| p.mode.map(|m| format!("{name}={m}")))
+        .collect();
+    if !overrides.is_empty() {
+        overrides.sort();
+        println!("  Mode overrides: {}", overrides.join(", "));
+    }
 
     if warnings.is_empty() {
         println!("\nConfig is valid.");
@@ -53,7 +91,7 @@ mod tests {
         std::fs::write(&tmp, toml::to_string_pretty(&cfg).unwrap()).unwrap();
 
         let result = execute(&tmp);
-        assert!(result.is_ok(), "validate should succeed: {:?}", result);
+        assert!(result.is_ok(), "validate should succeed: {result:?}");
 
         let _ = std::fs::remove_file(&tmp);
     }
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    fmt,
     path::{Path, PathBuf},
 };
 
@@ -21,6 +22,111 @@ pub struct ClinkConfig {
     pub verbose: bool,
     #[serde(default)]
     pub remote: Option<crate::remote::Remote>,
+    #[serde(default)]
+    pub complete_provider_action: CompleteProviderAction,
+    #[serde(default = "default_complete_provider_placeholder")]
+    pub complete_provider_placeholder: String,
+    #[serde(default)]
+    pub canonicalize: Option<crate::canonicalize::Canonicalize>,
+    #[serde(default = "default_selections")]
+    pub selections: Vec<Selection>,
+    #[serde(default)]
+    pub clipboard: crate::clipboard::ClipboardConfig,
+    #[serde(default)]
+    pub sensitive_patterns: Vec<String>,
+    #[serde(default)]
+    pub guards: crate::guard::Guards,
+    #[serde(default)]
+    pub notifications: crate::notify::Notifications,
+    #[serde(default = "default_history")]
+    pub history: bool,
+    #[serde(default = "crate::history::default_limit")]
+    pub history_limit: usize,
+    // 0 keeps entries until `history_limit` pushes them out.
+    #[serde(default = "crate::history::default_max_age_days")]
+    pub history_max_age_days: u32,
+    #[serde(default)]
+    pub history_redact: crate::history::HistoryRedact,
+}
+
+// What to do with a link whose provider is a ClearURLs `completeProvider`,
+// i.e. the whole domain exists to track or serve ads.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum CompleteProviderAction {
+    #[default]
+    #[serde(rename = "warn")]
+    Warn,
+    #[serde(rename = "placeholder")]
+    Placeholder,
+    #[serde(rename = "ignore")]
+    Ignore,
+}
+
+impl fmt::Display for CompleteProviderAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CompleteProviderAction::Warn => write!(f, "warn"),
+            CompleteProviderAction::Placeholder => write!(f, "placeholder"),
+            CompleteProviderAction::Ignore => write!(f, "ignore"),
+        }
+    }
+}
+
+fn default_complete_provider_placeholder() -> String {
+    "[tracking link removed]".into()
+}
+
+// The X11 selections the daemon watches. PRIMARY (whatever text is
+// highlighted, pasted with middle-click) is opt-in: rewriting it takes the
+// selection away from the app the text was highlighted in.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Selection {
+    #[serde(rename = "clipboard")]
+    Clipboard,
+    #[serde(rename = "primary")]
+    Primary,
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Selection::Clipboard => write!(f, "clipboard"),
+            Selection::Primary => write!(f, "primary"),
+        }
+    }
+}
+
+fn default_history() -> bool {
+    true
+}
+
+fn default_selections() -> Vec<Selection> {
+    vec![Selection::Clipboard]
+}
+
+pub fn describe_selections(selections: &[Selection]) -> String {
+    if selections.is_empty() {
+        return "none".to_string();
+    }
+    selections
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn describe_history(cfg: &ClinkConfig) -> String {
+    if !cfg.history {
+        return "off".to_string();
+    }
+    let age = match cfg.history_max_age_days {
+        0 => "no age limit".to_string(),
+        days => format!("{days} days"),
+    };
+    format!(
+        "{} entries, {age}, redact {}",
+        cfg.history_limit, cfg.history_redact
+    )
 }
 
 impl ClinkConfig {
@@ -35,6 +141,18 @@ impl ClinkConfig {
                 url: "https://rules2.clearurls.xyz/data.min.json".into(),
                 format: crate::remote::RemoteFormat::ClearUrls,
             }),
+            complete_provider_action: CompleteProviderAction::default(),
+            complete_provider_placeholder: default_complete_provider_placeholder(),
+            canonicalize: None,
+            selections: default_selections(),
+            clipboard: crate::clipboard::ClipboardConfig::default(),
+            sensitive_patterns: Vec::new(),
+            guards: crate::guard::Guards::default(),
+            notifications: crate::notify::Notifications::default(),
+            history: default_history(),
+            history_limit: crate::history::default_limit(),
+            history_max_age_days: crate::history::default_max_age_days(),
+            history_redact: crate::history::HistoryRedact::default(),
         }
     }
 
@@ -43,6 +161,18 @@ impl ClinkConfig {
         if self.sleep_duration == 0 {
             warnings.push("sleep_duration is 0, this will cause 100% CPU usage".to_string());
         }
+        if self.selections.is_empty() {
+            warnings.push("selections is empty, the daemon won't clean anything".to_string());
+        }
+        warnings.extend(crate::sensitive::check_patterns(&self.sensitive_patterns));
+        warnings.extend(crate::guard::check_guards(&self.guards));
+        warnings.extend(crate::notify::check_notifications(&self.notifications));
+        if self.history && self.history_limit == 0 {
+            warnings.push(
+                "history_limit is 0, so history keeps nothing; set history = false instead"
+                    .to_string(),
+            );
+        }
         warnings
     }
 }
@@ -179,6 +309,7 @@ fn migrate_old_config(config_path: &Path, raw: &toml::Value) -> Result<ClinkConf
         providers,
         verbose: false,
         remote,
+        ..ClinkConfig::default()
     };
 
     let backup_path = next_backup_path(config_path);
@@ -230,10 +361,65 @@ pub fn fallback_config_path(path: Option<PathBuf>) -> PathBuf {
 mod tests {
     use super::*;
 
+    #[test]
+    fn complete_provider_settings_default_when_absent() {
+        let cfg: ClinkConfig = toml::from_str(
+            "mode = 'remove'\nreplace_to = 'clink'\nsleep_duration = 150\n[providers]\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.complete_provider_action, CompleteProviderAction::Warn);
+        assert_eq!(cfg.complete_provider_placeholder, "[tracking link removed]");
+    }
+
+    #[test]
+    fn complete_provider_action_parses() {
+        let cfg: ClinkConfig = toml::from_str(
+            "mode = 'remove'\nreplace_to = 'clink'\nsleep_duration = 150\n\
+             complete_provider_action = 'placeholder'\n\
+             complete_provider_placeholder = '<ad>'\n[providers]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.complete_provider_action,
+            CompleteProviderAction::Placeholder
+        );
+        assert_eq!(cfg.complete_provider_placeholder, "<ad>");
+    }
+
+    #[test]
+    fn selections_default_to_clipboard_and_parse() {
+        let base = "mode = 'remove'\nreplace_to = 'clink'\nsleep_duration = 150\n";
+        let cfg: ClinkConfig = toml::from_str(&format!("{base}[providers]\n")).unwrap();
+        assert_eq!(cfg.selections, [Selection::Clipboard]);
+
+        let cfg: ClinkConfig = toml::from_str(&format!(
+            "{base}selections = ['clipboard', 'primary']\n[providers]\n"
+        ))
+        .unwrap();
+        assert_eq!(cfg.selections, [Selection::Clipboard, Selection::Primary]);
+        assert!(
+            toml::from_str::<ClinkConfig>(&format!(
+                "{base}selections = ['secondary']\n[providers]\n"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_selections() {
+        let cfg = ClinkConfig {
+            selections: Vec::new(),
+            ..ClinkConfig::default()
+        };
+        assert!(cfg.validate().iter().any(|w| w.contains("selections")));
+    }
+
     #[test]
     fn test_validate_zero_sleep_duration() {
-        let mut cfg = ClinkConfig::default();
-        cfg.sleep_duration = 0;
+        let cfg = ClinkConfig {
+            sleep_duration: 0,
+            ..ClinkConfig::default()
+        };
         let warnings = cfg.validate();
         assert!(warnings.iter().any(|w| w.contains("sleep_duration")));
     }
@@ -243,14 +429,14 @@ mod tests {
     // their setting is active but nothing reads it.
     #[test]
     fn config_rejects_unknown_top_level_field() {
-        let toml_str = r#"
+        let toml_str = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 typo_field = 'oops'
 
 [providers]
-"#;
+";
         let result = toml::from_str::<ClinkConfig>(toml_str);
         assert!(
             result.is_err(),
@@ -274,12 +460,12 @@ typo_field = 'oops'
         std::fs::create_dir_all(&dir).unwrap();
 
         let config_path = dir.join("config.toml");
-        let old_config = r#"
+        let old_config = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 params = ['fbclid']
-"#;
+";
         std::fs::write(&config_path, old_config).unwrap();
 
         let config = load_config(&config_path).unwrap();
@@ -307,13 +493,13 @@ params = ['fbclid']
         std::fs::create_dir_all(&dir).unwrap();
 
         let config_path = dir.join("config.toml");
-        let old_config = r#"
+        let old_config = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 params = ['fbclid', 'gclid', 'youtube.com``si']
 exit = [['exit.sc/', 'url']]
-"#;
+";
         std::fs::write(&config_path, old_config).unwrap();
 
         let config = load_config(&config_path).unwrap();
@@ -368,12 +554,12 @@ exit = [['exit.sc/', 'url']]
         let sentinel = "# this backup is from a previous migration; do not lose it\n";
         std::fs::write(&backup_path, sentinel).unwrap();
 
-        let old_config = r#"
+        let old_config = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 params = ['fbclid']
-"#;
+";
         std::fs::write(&config_path, old_config).unwrap();
 
         load_config(&config_path).unwrap();
@@ -440,14 +626,14 @@ params = ['fbclid']
         std::fs::create_dir_all(&dir).unwrap();
 
         let config_path = dir.join("config.toml");
-        let new_config = r#"
+        let new_config = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 
 [providers.global]
 rules = ['fbclid', 'gclid']
-"#;
+";
         std::fs::write(&config_path, new_config).unwrap();
 
         let config = load_config(&config_path).unwrap();
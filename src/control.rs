@@ -0,0 +1,391 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use nix::sys::stat::{Mode, umask};
+use nix::unistd::{Uid, geteuid};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{ClinkConfig, Selection};
+
+// The daemon listens on `runtime::socket_path()`. Each connection carries
+// one request and one response, both a single line of JSON:
+//
+//   -> {"command": "clean-text", "text": "https://a.test/?utm_source=x"}
+//   <- {"ok": true, "data": {"text": "https://a.test/", ...}}
+//
+// Requests are answered by the daemon loop itself, so a response always
+// reflects the state the loop is in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "command")]
+pub enum Request {
+    #[serde(rename = "reload")]
+    Reload,
+    #[serde(rename = "status")]
+    Status,
+    #[serde(rename = "stats")]
+    Stats,
+    // Without `seconds` the daemon stays paused until `resume`.
+    #[serde(rename = "pause")]
+    Pause {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seconds: Option<u64>,
+    },
+    #[serde(rename = "resume")]
+    Resume,
+    #[serde(rename = "undo")]
+    Undo,
+    // Puts `text` on the clipboard as it is, without cleaning it.
+    #[serde(rename = "copy")]
+    Copy { text: String },
+    #[serde(rename = "clean-text")]
+    CleanText { text: String },
+    #[serde(rename = "shutdown")]
+    Shutdown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+}
+
+impl Response {
+    pub fn ok(data: Value) -> Self {
+        Self {
+            ok: true,
+            error: None,
+            data,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            data: Value::Null,
+        }
+    }
+
+    pub fn into_result(self) -> Result<Value, String> {
+        if self.ok {
+            Ok(self.data)
+        } else {
+            Err(self
+                .error
+                .unwrap_or_else(|| "clink reported an unspecified error".to_string()))
+        }
+    }
+}
+
+// What a successful `reload` answers with.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ReloadReport {
+    pub providers: usize,
+    pub rules: usize,
+    pub redirections: usize,
+    pub warnings: Vec<String>,
+}
+
+impl ReloadReport {
+    pub fn new(cfg: &ClinkConfig, warnings: Vec<String>) -> Self {
+        Self {
+            providers: cfg.providers.len(),
+            rules: cfg.providers.values().map(|p| p.rules.len()).sum(),
+            redirections: cfg.providers.values().map(|p| p.redirections.len()).sum(),
+            warnings,
+        }
+    }
+}
+
+// What a successful `undo` answers with: the rewrite that was reverted.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UndoReport {
+    pub selection: Selection,
+    pub original: String,
+    pub cleaned: String,
+    // Older rewrites a further `undo` can still revert.
+    pub remaining: usize,
+}
+
+// Generous enough for `clean-text` with a large paste; a peer that sends
+// more is cut off rather than buffered without bound.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024 * 1024;
+// The daemon side only has to wait for a peer to write one line.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(2);
+// A reload can spend a while merging patterns before the loop answers.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// A request waiting for the daemon loop to answer it.
+pub struct Connection {
+    stream: UnixStream,
+}
+
+impl Connection {
+    pub fn reply(mut self, response: &Response) {
+        // The peer may have given up already; there's nobody to report to.
+        let _ = write_line(&mut self.stream, response);
+    }
+}
+
+pub type Requests = Receiver<(Request, Connection)>;
+
+// Binds the socket and accepts connections on a background thread. Parsed
+// requests are handed to the loop through the returned receiver; malformed
+// ones are answered right away.
+pub fn listen(path: &Path) -> Result<Requests, String> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!(
+                "another process is already listening on {}",
+                path.display()
+            ));
+        }
+        // Left behind by a daemon that didn't shut down cleanly.
+        fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale socket {}: {e}", path.display()))?;
+    }
+    // The runtime dir falls back to the shared temp dir on some systems;
+    // only the user running clink may talk to it. The umask keeps the
+    // socket private from the moment it exists.
+    let old_umask = umask(Mode::from_bits_truncate(0o077));
+    let bound = UnixListener::bind(path);
+    umask(old_umask);
+    let listener = bound.map_err(|e| format!("Failed to listen on {}: {e}", path.display()))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict {}: {e}", path.display()))?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let _ = stream.set_read_timeout(Some(SERVER_TIMEOUT));
+            let _ = stream.set_write_timeout(Some(SERVER_TIMEOUT));
+            let connection = Connection { stream };
+            if let Err(e) = check_peer(&connection.stream, geteuid()) {
+                connection.reply(&Response::error(e));
+                continue;
+            }
+            match read_request(&connection.stream) {
+                Ok(request) => {
+                    if tx.send((request, connection)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => connection.reply(&Response::error(e)),
+            }
+        }
+    });
+    Ok(rx)
+}
+
+// Both ends check the other runs as the same user, so neither a stranger
+// squatting on the socket path nor one connecting to it gets anywhere.
+fn check_peer(stream: &UnixStream, uid: Uid) -> Result<(), String> {
+    let peer = peer_uid(stream)
+        .map_err(|e| format!("Failed to check who is on the control socket: {e}"))?;
+    if peer != uid {
+        return Err(format!(
+            "The other end of the control socket runs as uid {peer}, not {uid}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> nix::Result<Uid> {
+    use nix::sys::socket::{getsockopt, sockopt};
+    getsockopt(stream, sockopt::PeerCredentials).map(|cred| Uid::from_raw(cred.uid()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> nix::Result<Uid> {
+    nix::unistd::getpeereid(stream).map(|(uid, _)| uid)
+}
+
+fn read_request(stream: &UnixStream) -> Result<Request, String> {
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_REQUEST_BYTES))
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read request: {e}"))?;
+    serde_json::from_str(&line).map_err(|e| format!("Invalid request: {e}"))
+}
+
+fn write_line(stream: &mut UnixStream, value: &impl Serialize) -> Result<(), String> {
+    let mut line = serde_json::to_string(value).map_err(|e| format!("Failed to serialize: {e}"))?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write to control socket: {e}"))
+}
+
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    // Fails when no daemon is listening; callers fall back to signals.
+    pub fn connect(path: &Path) -> Result<Self, String> {
+        let stream = UnixStream::connect(path)
+            .map_err(|e| format!("Failed to connect to {}: {e}", path.display()))?;
+        check_peer(&stream, geteuid())?;
+        let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+        Ok(Self { stream })
+    }
+
+    pub fn send(mut self, request: &Request) -> Result<Response, String> {
+        write_line(&mut self.stream, request)?;
+        let mut line = String::new();
+        BufReader::new(&self.stream)
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read response from clink: {e}"))?;
+        if line.is_empty() {
+            return Err("clink closed the control socket without answering".to_string());
+        }
+        serde_json::from_str(&line).map_err(|e| format!("Invalid response from clink: {e}"))
+    }
+}
+
+// For commands that only make sense with a daemon to talk to.
+pub fn request(request: &Request) -> Result<Value, String> {
+    let path = crate::runtime::socket_path();
+    let client = Client::connect(&path).map_err(|_| {
+        format!(
+            "clink is not running (nothing listening on {}).",
+            path.display()
+        )
+    })?;
+    client.send(request)?.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn socket_in(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("clink.sock")
+    }
+
+    #[test]
+    fn requests_use_the_command_tag() {
+        assert_eq!(
+            serde_json::to_string(&Request::CleanText { text: "x".into() }).unwrap(),
+            r#"{"command":"clean-text","text":"x"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"command":"reload"}"#).unwrap(),
+            Request::Reload
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"command":"pause"}"#).unwrap(),
+            Request::Pause { seconds: None }
+        );
+        assert_eq!(
+            serde_json::to_string(&Request::Pause { seconds: Some(60) }).unwrap(),
+            r#"{"command":"pause","seconds":60}"#
+        );
+        assert!(serde_json::from_str::<Request>(r#"{"command":"explode"}"#).is_err());
+        assert_eq!(
+            serde_json::to_string(&Response::ok(Value::Null)).unwrap(),
+            r#"{"ok":true}"#
+        );
+    }
+
+    #[test]
+    fn reload_report_counts_rules_and_redirections() {
+        let mut cfg = ClinkConfig::default();
+        cfg.providers.insert(
+            "extra".to_string(),
+            crate::provider::ProviderConfig {
+                rules: vec!["a".into(), "b".into()],
+                redirections: vec!["r".into()],
+                ..Default::default()
+            },
+        );
+        let report = ReloadReport::new(&cfg, vec!["w".into()]);
+        assert_eq!(report.providers, cfg.providers.len());
+        let rules: usize = cfg.providers.values().map(|p| p.rules.len()).sum();
+        assert_eq!(report.rules, rules);
+        assert!(report.redirections >= 1);
+        assert_eq!(report.warnings, ["w"]);
+    }
+
+    #[test]
+    fn round_trip_through_the_socket() {
+        let path = socket_in("clink_test_control_round_trip");
+        let requests = listen(&path).unwrap();
+        let client = thread::spawn({
+            let path = path.clone();
+            move || {
+                Client::connect(&path)
+                    .unwrap()
+                    .send(&Request::CleanText { text: "a".into() })
+            }
+        });
+        let (request, connection) = requests.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(request, Request::CleanText { text: "a".into() });
+        connection.reply(&Response::ok(serde_json::json!({"text": "b"})));
+        let data = client.join().unwrap().unwrap().into_result().unwrap();
+        assert_eq!(data["text"], "b");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected_by_the_listener() {
+        let path = socket_in("clink_test_control_malformed");
+        let _requests = listen(&path).unwrap();
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(b"not json\n").unwrap();
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert!(
+            response
+                .into_result()
+                .unwrap_err()
+                .starts_with("Invalid request")
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn peers_of_another_user_are_refused() {
+        let (ours, _theirs) = UnixStream::pair().unwrap();
+        assert!(check_peer(&ours, geteuid()).is_ok());
+        let stranger = Uid::from_raw(geteuid().as_raw().wrapping_add(1));
+        assert!(
+            check_peer(&ours, stranger)
+                .unwrap_err()
+                .starts_with("The other end of the control socket runs as uid")
+        );
+    }
+
+    #[test]
+    fn stale_socket_is_replaced_but_a_live_one_is_not() {
+        let path = socket_in("clink_test_control_stale");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        let _requests = listen(&path).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert!(listen(&path).is_err());
+        assert!(Client::connect(&std::env::temp_dir().join("clink_test_no_such.sock")).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -256,7 +256,7 @@ mod expand_url {
         assert!(
             result.is_err(),
             "exponential expansion must be rejected, got Ok with {} entries",
-            result.map(|v| v.len()).unwrap_or(0)
+            result.map_or(0, |v| v.len())
         );
     }
 }
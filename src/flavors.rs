@@ -0,0 +1,337 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::clink::{CleanResult, Clink};
+
+// Copying from a browser or office suite puts several representations of the
+// same content on the clipboard. clink cleans the ones that carry links and
+// writes them back together, so whichever flavor the paste target picks has
+// the same clean URLs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Flavors {
+    pub text: String,
+    pub html: Option<String>,
+    pub uri_list: Option<String>,
+}
+
+impl Flavors {
+    pub fn text(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+// Comments and the bodies of `<script>`/`<style>` aren't text: they are
+// passed through as they are. Only the opening tag of a script or style
+// element (groups 1 and 2) has attributes to clean.
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?is)<!--.*?(?:-->|\z)",
+        r"|(<script\b[^>]*>).*?(?:</script\s*>|\z)",
+        r"|(<style\b[^>]*>).*?(?:</style\s*>|\z)",
+        r"|<[^>]*>"
+    ))
+    .unwrap()
+});
+static ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(?:[a-zA-Z][a-zA-Z0-9]*|#[0-9]+|#[xX][0-9a-fA-F]+);").unwrap());
+static URL_ATTR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(\b(?:href|src)\s*=\s*)(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+))"#).unwrap()
+});
+
+// The same links usually appear in every flavor, so only one flavor is
+// counted: plain text, or the first rich flavor that cleaned something when
+// the text had nothing to clean (a copied link label hides its URL in the
+// `href`).
+pub fn clean(clink: &Clink, flavors: &Flavors) -> (Flavors, CleanResult) {
+    let text = clink.find_and_replace(&flavors.text);
+    let html = flavors.html.as_deref().map(|h| clean_html(clink, h));
+    let uri_list = flavors
+        .uri_list
+        .as_deref()
+        .map(|u| clean_uri_list(clink, u));
+
+    let cleaned = Flavors {
+        text: text.text.clone(),
+        html: html.as_ref().map(|r| r.text.clone()),
+        uri_list: uri_list.as_ref().map(|r| r.text.clone()),
+    };
+    let counted = match [html, uri_list]
+        .into_iter()
+        .flatten()
+        .find(|r| r.urls_cleaned > 0)
+    {
+        Some(rich) if text.urls_cleaned == 0 => CleanResult {
+            text: text.text,
+            ..rich
+        },
+        _ => text,
+    };
+    (cleaned, counted)
+}
+
+// Rewrites `href`/`src` attribute values and URLs in text nodes. Only the
+// links that changed are replaced; every other byte, entities included, is
+// kept as it was.
+fn clean_html(clink: &Clink, html: &str) -> CleanResult {
+    let mut total = CleanResult::default();
+    let mut out = String::with_capacity(html.len());
+    let mut last_end = 0;
+    for markup in MARKUP.captures_iter(html) {
+        let whole = markup.get(0).expect("group 0 always matches");
+        out.push_str(&clean_escaped(
+            clink,
+            &html[last_end..whole.start()],
+            escape_text,
+            &mut total,
+        ));
+        match markup.get(1).or_else(|| markup.get(2)) {
+            Some(open) => {
+                out.push_str(&clean_tag(clink, open.as_str(), &mut total));
+                out.push_str(&html[open.end()..whole.end()]);
+            }
+            None if whole.as_str().starts_with("<!--") => out.push_str(whole.as_str()),
+            None => out.push_str(&clean_tag(clink, whole.as_str(), &mut total)),
+        }
+        last_end = whole.end();
+    }
+    out.push_str(&clean_escaped(
+        clink,
+        &html[last_end..],
+        escape_text,
+        &mut total,
+    ));
+    total.text = out;
+    total
+}
+
+fn clean_tag(clink: &Clink, tag: &str, total: &mut CleanResult) -> String {
+    URL_ATTR
+        .replace_all(tag, |caps: &Captures| {
+            let (value, quote) = match (caps.get(2), caps.get(3), caps.get(4)) {
+                (Some(v), _, _) => (v.as_str(), Some('"')),
+                (_, Some(v), _) => (v.as_str(), Some('\'')),
+                (_, _, Some(v)) => (v.as_str(), None),
+                _ => unreachable!("one alternative always matches"),
+            };
+            let rewritten = clean_escaped(clink, value, |url| escape_attr(url, quote), total);
+            match quote {
+                Some(q) => format!("{}{q}{rewritten}{q}", &caps[1]),
+                None => format!("{}{rewritten}", &caps[1]),
+            }
+        })
+        .into_owned()
+}
+
+// Cleans the links in HTML-escaped text and splices back, escaped, only
+// the ones that changed.
+fn clean_escaped(
+    clink: &Clink,
+    escaped: &str,
+    escape: impl Fn(&str) -> String,
+    total: &mut CleanResult,
+) -> String {
+    let mut out = String::with_capacity(escaped.len());
+    let mut last_end = 0;
+    for link in clink.links(escaped) {
+        let (url, len) = unescape_link(link.as_str());
+        let result = clink.find_and_replace(&url);
+        if result.text != url {
+            out.push_str(&escaped[last_end..link.start()]);
+            out.push_str(&escape(&result.text));
+            last_end = link.start() + len;
+        }
+        tally(total, result);
+    }
+    out.push_str(&escaped[last_end..]);
+    out
+}
+
+// RFC 2483: one URI per line, `#` starts a comment. Line endings (CRLF per
+// the RFC, bare LF from some apps) are kept as they were.
+fn clean_uri_list(clink: &Clink, list: &str) -> CleanResult {
+    let mut total = CleanResult::default();
+    let mut out = String::with_capacity(list.len());
+    for line in list.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\r', '\n']);
+        if body.is_empty() || body.starts_with('#') {
+            out.push_str(line);
+            continue;
+        }
+        let result = clink.find_and_replace(body);
+        out.push_str(&result.text);
+        out.push_str(&line[body.len()..]);
+        tally(&mut total, result);
+    }
+    total.text = out;
+    total
+}
+
+fn tally(total: &mut CleanResult, part: CleanResult) {
+    total.urls_cleaned += part.urls_cleaned;
+    total.params_removed += part.params_removed;
+    total.exits_unwrapped += part.exits_unwrapped;
+    total.urls_canonicalized += part.urls_canonicalized;
+    total.warnings.extend(part.warnings);
+    total.rewritten.extend(part.rewritten);
+}
+
+// Decodes the `&amp;`s in a link found in escaped HTML. Any other entity
+// (`&nbsp;`, `&lt;`, ...) can't be part of a URL and ends the link there.
+// Returns the link and how many bytes of `escaped` it spans.
+fn unescape_link(escaped: &str) -> (String, usize) {
+    let mut url = String::with_capacity(escaped.len());
+    let mut last_end = 0;
+    for entity in ENTITY.find_iter(escaped) {
+        url.push_str(&escaped[last_end..entity.start()]);
+        last_end = entity.start();
+        let name = entity.as_str().to_ascii_lowercase();
+        if !matches!(name.as_str(), "&amp;" | "&#38;" | "&#x26;") {
+            return (url, last_end);
+        }
+        url.push('&');
+        last_end = entity.end();
+    }
+    url.push_str(&escaped[last_end..]);
+    (url, escaped.len())
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_attr(s: &str, quote: Option<char>) -> String {
+    let s = s.replace('&', "&amp;");
+    match quote {
+        Some('"') => s.replace('"', "&quot;"),
+        Some('\'') => s.replace('\'', "&#39;"),
+        _ => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClinkConfig;
+    use crate::provider::ProviderConfig;
+    use std::collections::HashMap;
+
+    fn test_clink() -> Clink {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "global".to_string(),
+            ProviderConfig {
+                rules: vec!["utm_source".into(), "fbclid".into()],
+                ..Default::default()
+            },
+        );
+        Clink::new(ClinkConfig {
+            providers,
+            remote: None,
+            ..ClinkConfig::default()
+        })
+    }
+
+    #[test]
+    fn cleans_href_src_and_text_nodes() {
+        let html = concat!(
+            r#"<p>See <a class="x" href="https://a.test/?id=1&amp;utm_source=x">the page</a>"#,
+            r"<img src='https://cdn.test/i.png?fbclid=1'>",
+            r" or https://b.test/?utm_source=y&amp;q=1</p>"
+        );
+        let result = clean_html(&test_clink(), html);
+        assert_eq!(
+            result.text,
+            concat!(
+                r#"<p>See <a class="x" href="https://a.test/?id=1">the page</a>"#,
+                r"<img src='https://cdn.test/i.png'>",
+                r" or https://b.test/?q=1</p>"
+            )
+        );
+        assert_eq!(result.urls_cleaned, 3);
+    }
+
+    #[test]
+    fn untouched_html_keeps_its_bytes() {
+        let html = r"<a HREF=https://a.test/?a=1&amp;b=2>https://a.test/?a=1&amp;b=2</a>";
+        let result = clean_html(&test_clink(), html);
+        assert_eq!(result.text, html);
+        assert_eq!(result.urls_cleaned, 0);
+    }
+
+    #[test]
+    fn rewritten_values_are_escaped_again() {
+        let html = r#"<a href="https://a.test/?a=1&amp;b=2&amp;fbclid=x">x</a>"#;
+        assert_eq!(
+            clean_html(&test_clink(), html).text,
+            r#"<a href="https://a.test/?a=1&amp;b=2">x</a>"#
+        );
+    }
+
+    #[test]
+    fn other_entities_scripts_and_comments_are_left_alone() {
+        let html = concat!(
+            "<p>Read&nbsp;https://a.test/?utm_source=x&amp;id=1&nbsp;&copy; 2024 &#47; a &amp; b</p>",
+            r#"<script src="https://cdn.test/s.js?fbclid=1">if (a && b) go("https://c.test/?utm_source=z");</script>"#,
+            "<style>a[href*='&'] {}</style><!-- a && b https://d.test/?utm_source=w -->"
+        );
+        let result = clean_html(&test_clink(), html);
+        assert_eq!(
+            result.text,
+            concat!(
+                "<p>Read&nbsp;https://a.test/?id=1&nbsp;&copy; 2024 &#47; a &amp; b</p>",
+                r#"<script src="https://cdn.test/s.js">if (a && b) go("https://c.test/?utm_source=z");</script>"#,
+                "<style>a[href*='&'] {}</style><!-- a && b https://d.test/?utm_source=w -->"
+            )
+        );
+        assert_eq!(result.urls_cleaned, 2);
+    }
+
+    #[test]
+    fn uri_list_keeps_comments_and_line_endings() {
+        let list = "# copied\r\nhttps://a.test/?fbclid=1\r\nhttps://b.test/\n";
+        let result = clean_uri_list(&test_clink(), list);
+        assert_eq!(
+            result.text,
+            "# copied\r\nhttps://a.test/\r\nhttps://b.test/\n"
+        );
+        assert_eq!(result.urls_cleaned, 1);
+    }
+
+    #[test]
+    fn counts_rich_flavor_when_text_has_no_links() {
+        let flavors = Flavors {
+            text: "the page".to_string(),
+            html: Some(r#"<a href="https://a.test/?utm_source=x">the page</a>"#.to_string()),
+            uri_list: None,
+        };
+        let (cleaned, result) = clean(&test_clink(), &flavors);
+        assert_eq!(cleaned.text, "the page");
+        assert_eq!(
+            cleaned.html.as_deref(),
+            Some(r#"<a href="https://a.test/">the page</a>"#)
+        );
+        assert_eq!(result.text, "the page");
+        assert_eq!(result.urls_cleaned, 1);
+        assert_eq!(result.params_removed, 1);
+    }
+
+    #[test]
+    fn counts_text_once_when_every_flavor_has_the_link() {
+        let link = "https://a.test/?utm_source=x";
+        let flavors = Flavors {
+            text: link.to_string(),
+            html: Some(format!(r#"<a href="{link}">{link}</a>"#)),
+            uri_list: Some(format!("{link}\r\n")),
+        };
+        let (cleaned, result) = clean(&test_clink(), &flavors);
+        assert_eq!(cleaned.text, "https://a.test/");
+        assert_eq!(cleaned.uri_list.as_deref(), Some("https://a.test/\r\n"));
+        assert_eq!(result.urls_cleaned, 1);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+use linkify::{LinkFinder, LinkKind};
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+// The `[guards]` table: checks the daemon runs on new clipboard content
+// before cleaning it. Anything a guard skips is left as it was copied.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Guards {
+    // 0 turns the limit off.
+    pub max_bytes: usize,
+    pub only_when_clipboard_is_single_url: bool,
+    pub skip_patterns: Vec<String>,
+}
+
+impl Default for Guards {
+    fn default() -> Self {
+        Self {
+            max_bytes: 1024 * 1024,
+            only_when_clipboard_is_single_url: false,
+            skip_patterns: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Guards {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.max_bytes {
+            0 => write!(f, "no size limit")?,
+            n => write!(f, "max_bytes {n}")?,
+        }
+        if self.only_when_clipboard_is_single_url {
+            write!(f, ", single URL only")?;
+        }
+        if !self.skip_patterns.is_empty() {
+            write!(f, ", {} skip_patterns", self.skip_patterns.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skip {
+    TooLarge,
+    Pattern,
+    NotSingleUrl,
+}
+
+// `Guards` with the skip patterns compiled. Invalid patterns are dropped
+// here and reported by `check_guards`.
+pub struct CompiledGuards {
+    max_bytes: usize,
+    single_url: bool,
+    skip: RegexSet,
+    finder: LinkFinder,
+}
+
+impl CompiledGuards {
+    pub fn new(guards: &Guards) -> Self {
+        let valid: Vec<&String> = guards
+            .skip_patterns
+            .iter()
+            .filter(|p| Regex::new(p).is_ok())
+            .collect();
+        let mut finder = LinkFinder::new();
+        finder.kinds(&[LinkKind::Url]);
+        Self {
+            max_bytes: guards.max_bytes,
+            single_url: guards.only_when_clipboard_is_single_url,
+            skip: RegexSet::new(valid).unwrap_or_else(|_| RegexSet::empty()),
+            finder,
+        }
+    }
+
+    pub fn too_large(&self, text: &str) -> bool {
+        self.max_bytes > 0 && text.len() > self.max_bytes
+    }
+
+    // Cheapest check first: the size limit exists so a huge paste never
+    // reaches a regex.
+    pub fn check(&self, text: &str) -> Option<Skip> {
+        if self.too_large(text) {
+            return Some(Skip::TooLarge);
+        }
+        if self.skip.is_match(text) {
+            return Some(Skip::Pattern);
+        }
+        if self.single_url && !self.is_single_url(text) {
+            return Some(Skip::NotSingleUrl);
+        }
+        None
+    }
+
+    // One link and nothing else but surrounding whitespace, the way a link
+    // copied from an address bar or a "copy link" menu looks.
+    fn is_single_url(&self, text: &str) -> bool {
+        let trimmed = text.trim();
+        let mut links = self.finder.links(trimmed);
+        matches!(
+            (links.next(), links.next()),
+            (Some(link), None) if link.start() == 0 && link.end() == trimmed.len()
+        )
+    }
+}
+
+pub fn check_guards(guards: &Guards) -> Vec<String> {
+    guards
+        .skip_patterns
+        .iter()
+        .filter_map(|p| {
+            Regex::new(p)
+                .err()
+                .map(|e| format!("[guards] skip_patterns '{p}' failed to compile: {e}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_only_limits_size() {
+        let guards = CompiledGuards::new(&Guards::default());
+        assert_eq!(guards.check("see https://a.test/?utm_source=x"), None);
+        assert_eq!(
+            guards.check(&"x".repeat(1024 * 1024 + 1)),
+            Some(Skip::TooLarge)
+        );
+    }
+
+    #[test]
+    fn zero_max_bytes_disables_the_limit() {
+        let guards = CompiledGuards::new(&Guards {
+            max_bytes: 0,
+            ..Guards::default()
+        });
+        assert_eq!(guards.check(&"x".repeat(2 * 1024 * 1024)), None);
+        assert_eq!(
+            Guards {
+                max_bytes: 0,
+                only_when_clipboard_is_single_url: true,
+                skip_patterns: vec!["a".into()],
+            }
+            .to_string(),
+            "no size limit, single URL only, 1 skip_patterns"
+        );
+    }
+
+    #[test]
+    fn single_url_allows_only_a_bare_link() {
+        let guards = CompiledGuards::new(&Guards {
+            only_when_clipboard_is_single_url: true,
+            ..Guards::default()
+        });
+        assert_eq!(guards.check("  https://a.test/?utm_source=x\n"), None);
+        for text in [
+            "see https://a.test/",
+            "https://a.test/ https://b.test/",
+            r#"{"url": "https://a.test/?utm_source=x"}"#,
+            "no links here",
+        ] {
+            assert_eq!(guards.check(text), Some(Skip::NotSingleUrl), "{text}");
+        }
+    }
+
+    #[test]
+    fn skip_patterns_match_anywhere() {
+        let guards = Guards {
+            skip_patterns: vec![r"^\s*[\[{]".to_string(), "(".to_string()],
+            ..Guards::default()
+        };
+        let compiled = CompiledGuards::new(&guards);
+        assert_eq!(
+            compiled.check(r#"{"url": "https://a.test/?utm_source=x"}"#),
+            Some(Skip::Pattern)
+        );
+        assert_eq!(compiled.check("https://a.test/?utm_source=x"), None);
+        let warnings = check_guards(&guards);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("[guards] skip_patterns '(' failed to compile"));
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use linkify::{LinkFinder, LinkKind};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::clink::CleanResult;
+use crate::config::{ClinkConfig, Selection};
+
+// How much of a rewrite `history` keeps. Even the cleaned text can say more
+// than the links in it, so by default only the links are stored, and the
+// originals lose the query strings and fragments clink was there to remove.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum HistoryRedact {
+    // The whole clipboard text.
+    #[serde(rename = "none")]
+    None,
+    // Only the links, one per line.
+    #[serde(rename = "text")]
+    Text,
+    // Like `text`, but the original links go without their query strings
+    // and fragments.
+    #[default]
+    #[serde(rename = "original")]
+    Original,
+    // Only the links, without their query strings and fragments.
+    #[serde(rename = "query")]
+    Query,
+}
+
+impl HistoryRedact {
+    // How the original and the cleaned text of an entry are redacted.
+    fn sides(self) -> (Self, Self) {
+        match self {
+            HistoryRedact::Original => (HistoryRedact::Query, HistoryRedact::Text),
+            other => (other, other),
+        }
+    }
+}
+
+impl fmt::Display for HistoryRedact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HistoryRedact::None => write!(f, "none"),
+            HistoryRedact::Text => write!(f, "text"),
+            HistoryRedact::Original => write!(f, "original"),
+            HistoryRedact::Query => write!(f, "query"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+pub fn default_limit() -> usize {
+    500
+}
+
+pub fn default_max_age_days() -> u32 {
+    30
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // RFC 3339, local time.
+    pub time: String,
+    pub selection: Selection,
+    pub original: String,
+    pub cleaned: String,
+    pub urls_cleaned: u32,
+    pub params_removed: u32,
+    pub exits_unwrapped: u32,
+}
+
+impl Entry {
+    pub fn new(
+        selection: Selection,
+        original: &str,
+        result: &CleanResult,
+        redact: HistoryRedact,
+    ) -> Self {
+        let (original_redact, cleaned_redact) = redact.sides();
+        Self {
+            time: chrono::Local::now().to_rfc3339(),
+            selection,
+            original: apply_redaction(original, original_redact),
+            cleaned: apply_redaction(&result.text, cleaned_redact),
+            urls_cleaned: result.urls_cleaned,
+            params_removed: result.params_removed,
+            exits_unwrapped: result.exits_unwrapped,
+        }
+    }
+
+    pub fn matches(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.original.to_lowercase().contains(&search)
+            || self.cleaned.to_lowercase().contains(&search)
+    }
+}
+
+pub fn apply_redaction(text: &str, redact: HistoryRedact) -> String {
+    if redact == HistoryRedact::None {
+        return text.to_string();
+    }
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+    finder
+        .links(text)
+        .map(|link| match (redact, Url::parse(link.as_str())) {
+            (HistoryRedact::Query, Ok(mut url)) => {
+                url.set_query(None);
+                url.set_fragment(None);
+                url.to_string()
+            }
+            _ => link.as_str().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The history file, one JSON entry per line, oldest first. The daemon
+// appends to it and rewrites it whenever it grows past the limit.
+pub struct History {
+    path: PathBuf,
+    limit: usize,
+    max_age_days: u32,
+    redact: HistoryRedact,
+    len: usize,
+}
+
+impl History {
+    // None when the config turns history off. Call `prune` once opened, so
+    // a lowered limit or an expired entry is dealt with right away.
+    pub fn open(path: PathBuf, cfg: &ClinkConfig) -> Option<Self> {
+        cfg.history.then_some(Self {
+            path,
+            limit: cfg.history_limit,
+            max_age_days: cfg.history_max_age_days,
+            redact: cfg.history_redact,
+            len: 0,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        selection: Selection,
+        original: &str,
+        result: &CleanResult,
+    ) -> Result<(), String> {
+        let entry = Entry::new(selection, original, result, self.redact);
+        append(&self.path, &entry)?;
+        self.len += 1;
+        // Rewriting the file on every entry past the limit would make each
+        // copy cost a full rewrite; a little slack batches them.
+        if self.len > self.limit + self.limit / 10 {
+            self.prune()?;
+        }
+        Ok(())
+    }
+
+    pub fn prune(&mut self) -> Result<(), String> {
+        let mut entries = load(&self.path);
+        let before = entries.len();
+        if self.max_age_days > 0 {
+            let cutoff = chrono::Local::now() - chrono::Duration::days(self.max_age_days.into());
+            entries.retain(|e| {
+                chrono::DateTime::parse_from_rfc3339(&e.time).is_ok_and(|t| t >= cutoff)
+            });
+        }
+        let excess = entries.len().saturating_sub(self.limit);
+        entries.drain(..excess);
+        self.len = entries.len();
+        if entries.len() == before {
+            return Ok(());
+        }
+        save(&self.path, &entries)
+    }
+}
+
+// Entries that don't parse (a line cut short by a crash) are skipped.
+pub fn load(path: &Path) -> Vec<Entry> {
+    fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize: {e}"))?;
+    let mut file = private_options()
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    writeln!(file, "{line}").map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+// Like `runtime::write_atomic`, but the file is private from the start.
+fn save(path: &Path, entries: &[Entry]) -> Result<(), String> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(
+            &serde_json::to_string(entry).map_err(|e| format!("Failed to serialize: {e}"))?,
+        );
+        content.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    let _ = fs::remove_file(&tmp);
+    private_options()
+        .write(true)
+        .open(&tmp)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {e}", tmp.display()))?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!(
+            "Failed to rename {} to {}: {e}",
+            tmp.display(),
+            path.display()
+        )
+    })
+}
+
+pub fn clear(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {e}", path.display())),
+    }
+}
+
+// Links someone else could read are exactly what the history must not leak.
+#[cfg(unix)]
+fn private_options() -> fs::OpenOptions {
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut options = fs::OpenOptions::new();
+    options.create(true).mode(0o600);
+    options
+}
+
+#[cfg(not(unix))]
+fn private_options() -> fs::OpenOptions {
+    let mut options = fs::OpenOptions::new();
+    options.create(true);
+    options
+}
+
+pub fn to_csv(entries: &[Entry]) -> String {
+    let mut lines = vec![
+        "time,selection,urls_cleaned,params_removed,exits_unwrapped,original,cleaned".to_string(),
+    ];
+    lines.extend(entries.iter().map(|e| {
+        [
+            csv_field(&e.time),
+            e.selection.to_string(),
+            e.urls_cleaned.to_string(),
+            e.params_removed.to_string(),
+            e.exits_unwrapped.to_string(),
+            csv_field(&e.original),
+            csv_field(&e.cleaned),
+        ]
+        .join(",")
+    }));
+    lines.join("\n")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(text: &str) -> CleanResult {
+        CleanResult {
+            text: text.to_string(),
+            urls_cleaned: 1,
+            params_removed: 1,
+            ..CleanResult::default()
+        }
+    }
+
+    fn history_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("history.jsonl")
+    }
+
+    #[test]
+    fn redaction_keeps_only_what_is_asked_for() {
+        let text = "see https://a.test/p?utm_source=x#top, then call me";
+        assert_eq!(apply_redaction(text, HistoryRedact::None), text);
+        assert_eq!(
+            apply_redaction(text, HistoryRedact::Text),
+            "https://a.test/p?utm_source=x#top"
+        );
+        assert_eq!(
+            apply_redaction(text, HistoryRedact::Query),
+            "https://a.test/p"
+        );
+        assert_eq!(apply_redaction("no links", HistoryRedact::Text), "");
+
+        // By default the cleaned link is kept whole, the original without
+        // anything that could identify the click.
+        let entry = Entry::new(
+            Selection::Clipboard,
+            text,
+            &result("see https://a.test/p?id=1, then call me"),
+            HistoryRedact::default(),
+        );
+        assert_eq!(entry.original, "https://a.test/p");
+        assert_eq!(entry.cleaned, "https://a.test/p?id=1");
+    }
+
+    #[test]
+    fn history_is_bounded_and_private() {
+        let path = history_file("clink_test_history_limit");
+        let cfg = ClinkConfig {
+            history_limit: 10,
+            ..ClinkConfig::default()
+        };
+        let mut history = History::open(path.clone(), &cfg).unwrap();
+        for i in 0..25 {
+            history
+                .record(
+                    Selection::Clipboard,
+                    &format!("https://a.test/{i}?utm_source=x"),
+                    &result(&format!("https://a.test/{i}")),
+                )
+                .unwrap();
+        }
+        let entries = load(&path);
+        assert!(entries.len() <= 11, "{}", entries.len());
+        assert_eq!(entries.last().unwrap().cleaned, "https://a.test/24");
+
+        History::open(path.clone(), &cfg).unwrap().prune().unwrap();
+        let entries = load(&path);
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0].cleaned, "https://a.test/15");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn old_entries_expire_and_history_can_be_off() {
+        let path = history_file("clink_test_history_age");
+        let mut old = Entry::new(
+            Selection::Clipboard,
+            "https://a.test/?utm_source=x",
+            &result("https://a.test/"),
+            HistoryRedact::Text,
+        );
+        old.time = (chrono::Local::now() - chrono::Duration::days(40)).to_rfc3339();
+        append(&path, &old).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"cut short\n")
+            .unwrap();
+
+        let cfg = ClinkConfig::default();
+        History::open(path.clone(), &cfg).unwrap().prune().unwrap();
+        assert!(load(&path).is_empty());
+        assert!(
+            History::open(
+                path.clone(),
+                &ClinkConfig {
+                    history: false,
+                    ..ClinkConfig::default()
+                }
+            )
+            .is_none()
+        );
+        clear(&path).unwrap();
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let entry = Entry {
+            time: "2026-04-09T10:00:00+00:00".into(),
+            selection: Selection::Primary,
+            original: "https://a.test/?a=1,2&utm_source=\"x\"".into(),
+            cleaned: "https://a.test/?a=1,2".into(),
+            urls_cleaned: 1,
+            params_removed: 1,
+            exits_unwrapped: 0,
+        };
+        assert_eq!(
+            to_csv(&[entry]),
+            "time,selection,urls_cleaned,params_removed,exits_unwrapped,original,cleaned\n\
+             2026-04-09T10:00:00+00:00,primary,1,1,0,\"https://a.test/?a=1,2&utm_source=\"\"x\"\"\",\"https://a.test/?a=1,2\""
+        );
+    }
+}
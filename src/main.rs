@@ -1,15 +1,25 @@
 mod builtin;
+mod canonicalize;
 mod clearurls;
 mod cli;
 mod clink;
+mod clipboard;
 mod commands;
 mod config;
+#[cfg(unix)]
+mod control;
 mod expand_string;
+mod flavors;
+mod guard;
+mod history;
 mod migration;
 mod mode;
+mod notify;
 mod provider;
+mod query;
 mod remote;
 mod runtime;
+mod sensitive;
 mod service;
 #[cfg(unix)]
 mod signal;
@@ -27,7 +37,10 @@ fn main() {
         .unwrap_or_else(|| fallback_config_path(config_dir()));
 
     let result = match cli.command {
-        None | Some(Command::Run) => commands::run::execute(&config_path, cli.verbose),
+        None => commands::run::execute(&config_path, cli.verbose, false),
+        Some(Command::Run { dry_run }) => {
+            commands::run::execute(&config_path, cli.verbose, dry_run)
+        }
         Some(Command::Init) => commands::init::execute(&config_path),
         Some(Command::Install) => commands::install::execute(&config_path),
         Some(Command::Uninstall) => commands::uninstall::execute(),
@@ -35,9 +48,41 @@ fn main() {
         Some(Command::Reload) => commands::reload::execute(),
         Some(Command::Restart) => commands::restart::execute(&config_path, cli.verbose),
         Some(Command::State) => commands::state::execute(),
+        Some(Command::Pause { duration }) => commands::pause::execute(duration),
+        Some(Command::Resume) => commands::resume::execute(),
+        Some(Command::Undo { exclude }) => commands::undo::execute(&config_path, exclude),
+        Some(Command::History {
+            search,
+            limit,
+            copy,
+            original,
+            export,
+            clear,
+        }) => {
+            if clear {
+                commands::history::clear()
+            } else {
+                commands::history::execute(
+                    &config_path,
+                    search.as_deref(),
+                    limit,
+                    copy.map(|n| (n, original)),
+                    export,
+                )
+            }
+        }
         Some(Command::Config { diff, reset }) => {
             commands::config::execute(&config_path, diff, reset)
         }
+        Some(Command::Clean { urls, mode }) => commands::clean::execute(&config_path, &urls, mode)
+            .map(|changed| {
+                if changed {
+                    std::process::exit(commands::clean::EXIT_CHANGED);
+                }
+            }),
+        Some(Command::Explain { urls, json }) => {
+            commands::explain::execute(&config_path, &urls, json)
+        }
         Some(Command::Update { write_snapshot }) => {
             commands::update::execute(&config_path, write_snapshot.as_deref())
         }
@@ -1,16 +1,24 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, ValueEnum)]
 pub enum Mode {
     #[serde(rename = "remove")]
+    #[value(name = "remove")]
     Remove,
     #[serde(rename = "replace")]
+    #[value(name = "replace")]
     Replace,
     #[serde(rename = "your_mom")]
+    #[value(name = "your_mom")]
     YourMom,
     #[serde(rename = "evil")]
+    #[value(name = "evil")]
     Evil,
+    #[serde(rename = "off")]
+    #[value(name = "off")]
+    Off,
 }
 
 impl fmt::Display for Mode {
@@ -20,6 +28,7 @@ impl fmt::Display for Mode {
             Mode::Replace => write!(f, "Replace"),
             Mode::YourMom => write!(f, "YourMom"),
             Mode::Evil => write!(f, "Evil"),
+            Mode::Off => write!(f, "Off"),
         }
     }
 }
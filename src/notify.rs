@@ -0,0 +1,625 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clink::CleanResult;
+
+// The `[notifications]` table: a desktop notification, with an Undo
+// button, whenever the daemon rewrites a link.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notifications {
+    pub enabled: bool,
+    // Seconds. Rewrites closer together than this share one notification;
+    // 0 notifies every one.
+    pub min_interval: u64,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_interval: 10,
+        }
+    }
+}
+
+impl fmt::Display for Notifications {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.enabled, self.min_interval) {
+            (false, _) => write!(f, "off"),
+            (true, 0) => write!(f, "on, every rewrite"),
+            (true, n) => write!(f, "on, at most one every {n}s"),
+        }
+    }
+}
+
+pub fn check_notifications(notifications: &Notifications) -> Vec<String> {
+    if notifications.enabled && !cfg!(all(unix, not(target_os = "macos"))) {
+        return vec![
+            "[notifications] need a freedesktop D-Bus session, which this platform doesn't have"
+                .to_string(),
+        ];
+    }
+    Vec::new()
+}
+
+// Lets a notification through at most once per `interval`.
+pub struct RateLimit {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl RateLimit {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    // How long until `allow` lets the next one through.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.last.map_or(Duration::ZERO, |last| {
+            self.interval.saturating_sub(now.duration_since(last))
+        })
+    }
+
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+// Summary and body of the notification for a rewrite. `held_back` counts
+// the rewrites the rate limit kept quiet since the previous one.
+pub fn message(result: &CleanResult, held_back: u32) -> (String, String) {
+    let summary = match result.urls_cleaned {
+        1 => "clink cleaned a link".to_string(),
+        n => format!("clink cleaned {n} links"),
+    };
+    let mut done = Vec::new();
+    if result.params_removed > 0 {
+        done.push(format!(
+            "removed {}",
+            plural(result.params_removed, "tracking parameter")
+        ));
+    }
+    if result.exits_unwrapped > 0 {
+        done.push(format!(
+            "unwrapped {}",
+            plural(result.exits_unwrapped, "redirect")
+        ));
+    }
+    let body = match done.join(" and ") {
+        done if done.is_empty() => "Rewritten according to your rules.".to_string(),
+        done => format!("{}{}.", done[..1].to_uppercase(), &done[1..]),
+    };
+    if held_back == 0 {
+        return (summary, body);
+    }
+    let more = plural(held_back, "more rewrite");
+    (
+        summary,
+        format!("{body}\n{more} since the last notification."),
+    )
+}
+
+fn plural(n: u32, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+// What the notifier reports back to the daemon loop.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    // Undo was clicked on the notification about the rewrite `notify`
+    // returned this token for.
+    Undo(u64),
+    Failed(String),
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use self::dbus::Notifier;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod dbus {
+    use std::collections::HashMap;
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use zbus::blocking::{Proxy, connection, proxy};
+    use zbus::proxy::CacheProperties;
+    use zbus::zvariant::Value;
+
+    use super::{Event, Notifications, RateLimit, message};
+    use crate::clink::CleanResult;
+
+    const APP_NAME: &str = "clink";
+    const UNDO_ACTION: &str = "undo";
+    // A notification server that doesn't answer by then is treated as
+    // gone; the default D-Bus timeout is 25s.
+    const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+    enum Job {
+        Notify(u64, CleanResult),
+        MinInterval(Duration),
+    }
+
+    enum Message {
+        Shown { id: u32, token: u64 },
+        Clicked(u32),
+        Failed(String),
+    }
+
+    // Talks to whatever implements org.freedesktop.Notifications on the
+    // session bus. The calls happen on a thread of their own, so a slow
+    // notification server never holds up the clipboard. Each notification
+    // replaces the previous one, so only the latest rewrite ever has an
+    // Undo button on screen.
+    pub struct Notifier {
+        jobs: Sender<Job>,
+        messages: Receiver<Message>,
+        // Id and token of the notification on screen.
+        shown: Option<(u32, u64)>,
+        next_token: u64,
+    }
+
+    impl Notifier {
+        pub fn connect(cfg: &Notifications) -> Result<Self, String> {
+            Self::start(connection::Builder::session(), cfg)
+        }
+
+        fn start(
+            builder: zbus::Result<connection::Builder>,
+            cfg: &Notifications,
+        ) -> Result<Self, String> {
+            let connection = builder
+                .and_then(|b| b.method_timeout(CALL_TIMEOUT).build())
+                .map_err(|e| format!("Failed to connect to the D-Bus session bus: {e}"))?;
+            let proxy: Proxy<'static> = proxy::Builder::new(&connection)
+                .destination("org.freedesktop.Notifications")
+                .and_then(|b| b.path("/org/freedesktop/Notifications"))
+                .and_then(|b| b.interface("org.freedesktop.Notifications"))
+                .and_then(|b| b.cache_properties(CacheProperties::No).build())
+                .map_err(|e| format!("Failed to set up notifications: {e}"))?;
+            let signals = proxy
+                .receive_signal("ActionInvoked")
+                .map_err(|e| format!("Failed to subscribe to notification actions: {e}"))?;
+            let (tx, messages) = mpsc::channel();
+            let clicks = tx.clone();
+            // Ends with the first click after the notifier is dropped.
+            thread::spawn(move || {
+                for signal in signals {
+                    let Ok((id, action)) = signal.body().deserialize::<(u32, String)>() else {
+                        continue;
+                    };
+                    if action == UNDO_ACTION && clicks.send(Message::Clicked(id)).is_err() {
+                        break;
+                    }
+                }
+            });
+            let (jobs, rx) = mpsc::channel();
+            let limit = RateLimit::new(Duration::from_secs(cfg.min_interval));
+            // Ends when the notifier is dropped.
+            thread::spawn(move || send_notifications(&proxy, limit, &rx, &tx));
+            Ok(Self {
+                jobs,
+                messages,
+                shown: None,
+                next_token: 0,
+            })
+        }
+
+        pub fn set_min_interval(&mut self, seconds: u64) {
+            let _ = self
+                .jobs
+                .send(Job::MinInterval(Duration::from_secs(seconds)));
+        }
+
+        // Queues a notification about the rewrite and returns the token an
+        // Undo click on it is reported with.
+        pub fn notify(&mut self, result: &CleanResult) -> u64 {
+            self.next_token += 1;
+            // Only the counts go to the notification, not the text.
+            let counts = CleanResult {
+                urls_cleaned: result.urls_cleaned,
+                params_removed: result.params_removed,
+                exits_unwrapped: result.exits_unwrapped,
+                ..CleanResult::default()
+            };
+            let _ = self.jobs.send(Job::Notify(self.next_token, counts));
+            self.next_token
+        }
+
+        // What happened since the last call. Clicks on a notification that
+        // has been replaced don't count.
+        pub fn events(&mut self) -> Vec<Event> {
+            let mut events = Vec::new();
+            for message in self.messages.try_iter() {
+                match message {
+                    Message::Shown { id, token } => self.shown = Some((id, token)),
+                    Message::Clicked(id) => {
+                        if let Some((_, token)) = self.shown.filter(|&(shown, _)| shown == id) {
+                            self.shown = None;
+                            events.push(Event::Undo(token));
+                        }
+                    }
+                    Message::Failed(e) => events.push(Event::Failed(e)),
+                }
+            }
+            events
+        }
+    }
+
+    // The notifier thread. A rewrite the rate limit holds back is shown
+    // when the interval ends, unless a later one takes its place first;
+    // either way the notification counts the rest that went unmentioned.
+    fn send_notifications(
+        proxy: &Proxy,
+        mut limit: RateLimit,
+        jobs: &Receiver<Job>,
+        messages: &Sender<Message>,
+    ) {
+        let mut held: Option<(u64, CleanResult)> = None;
+        // Held back rewrites older than `held`.
+        let mut skipped = 0;
+        let mut shown = 0;
+        loop {
+            let job = if held.is_some() {
+                match jobs.recv_timeout(limit.remaining(Instant::now())) {
+                    Ok(job) => Some(job),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            } else {
+                match jobs.recv() {
+                    Ok(job) => Some(job),
+                    Err(_) => return,
+                }
+            };
+            let (token, result, held_back) = match job {
+                Some(Job::MinInterval(interval)) => {
+                    limit.interval = interval;
+                    continue;
+                }
+                Some(Job::Notify(token, result)) => {
+                    if !limit.allow(Instant::now()) {
+                        if held.replace((token, result)).is_some() {
+                            skipped += 1;
+                        }
+                        continue;
+                    }
+                    let held_back = skipped + u32::from(held.take().is_some());
+                    (token, result, held_back)
+                }
+                None => {
+                    if !limit.allow(Instant::now()) {
+                        continue;
+                    }
+                    let Some((token, result)) = held.take() else {
+                        continue;
+                    };
+                    (token, result, skipped)
+                }
+            };
+            skipped = 0;
+            let (summary, body) = message(&result, held_back);
+            let hints: HashMap<&str, Value> = HashMap::new();
+            let sent = match proxy.call(
+                "Notify",
+                &(
+                    APP_NAME,
+                    shown,
+                    "edit-paste",
+                    summary.as_str(),
+                    body.as_str(),
+                    vec![UNDO_ACTION, "Undo"],
+                    hints,
+                    -1_i32,
+                ),
+            ) {
+                Ok(id) => {
+                    shown = id;
+                    messages.send(Message::Shown { id, token })
+                }
+                Err(e) => {
+                    messages.send(Message::Failed(format!("Failed to show notification: {e}")))
+                }
+            };
+            if sent.is_err() {
+                return;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::{BufRead, BufReader};
+        use std::process::{Child, Command, Stdio};
+        use std::sync::{Arc, Mutex};
+        use zbus::object_server::SignalEmitter;
+
+        struct Shown {
+            id: u32,
+            app_name: String,
+            summary: String,
+            body: String,
+            actions: Vec<String>,
+        }
+
+        // Records what it is sent, like a notification daemon would show it.
+        struct Server {
+            shown: Arc<Mutex<Vec<Shown>>>,
+        }
+
+        #[zbus::interface(name = "org.freedesktop.Notifications")]
+        impl Server {
+            #[allow(clippy::too_many_arguments)]
+            fn notify(
+                &self,
+                app_name: &str,
+                replaces_id: u32,
+                app_icon: &str,
+                summary: &str,
+                body: &str,
+                actions: Vec<String>,
+                hints: HashMap<&str, Value<'_>>,
+                expire_timeout: i32,
+            ) -> u32 {
+                let _ = (app_icon, hints, expire_timeout);
+                let mut shown = self.shown.lock().unwrap();
+                let id = if replaces_id == 0 {
+                    u32::try_from(shown.len()).unwrap() + 1
+                } else {
+                    replaces_id
+                };
+                shown.push(Shown {
+                    id,
+                    app_name: app_name.to_string(),
+                    summary: summary.to_string(),
+                    body: body.to_string(),
+                    actions,
+                });
+                id
+            }
+
+            #[zbus(signal)]
+            async fn action_invoked(
+                emitter: &SignalEmitter<'_>,
+                id: u32,
+                action_key: &str,
+            ) -> zbus::Result<()>;
+        }
+
+        struct Bus {
+            daemon: Child,
+            address: String,
+        }
+
+        impl Drop for Bus {
+            fn drop(&mut self) {
+                let _ = self.daemon.kill();
+                let _ = self.daemon.wait();
+            }
+        }
+
+        fn private_bus() -> Bus {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Bus {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        fn cleaned() -> CleanResult {
+            CleanResult {
+                urls_cleaned: 1,
+                params_removed: 2,
+                ..CleanResult::default()
+            }
+        }
+
+        fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !done() {
+                assert!(Instant::now() < deadline, "{what} never happened");
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+
+        #[test]
+        #[ignore = "needs dbus-daemon on PATH, e.g. `cargo test -- --ignored`"]
+        fn notifies_on_a_private_bus_and_reports_undo_clicks() {
+            let bus = private_bus();
+            let shown = Arc::new(Mutex::new(Vec::new()));
+            let server = connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .name("org.freedesktop.Notifications")
+                .unwrap()
+                .serve_at(
+                    "/org/freedesktop/Notifications",
+                    Server {
+                        shown: Arc::clone(&shown),
+                    },
+                )
+                .unwrap()
+                .build()
+                .unwrap();
+
+            let mut notifier = Notifier::start(
+                connection::Builder::address(bus.address.as_str()),
+                &Notifications {
+                    enabled: true,
+                    min_interval: 1,
+                },
+            )
+            .unwrap();
+            let first = notifier.notify(&cleaned());
+            wait_for("the first notification", || {
+                shown.lock().unwrap().len() == 1
+            });
+            // Both are held back; the later one is shown when the interval
+            // ends and counts the other.
+            notifier.notify(&cleaned());
+            let last = notifier.notify(&cleaned());
+            wait_for("the held back notification", || {
+                shown.lock().unwrap().len() == 2
+            });
+            {
+                let shown = shown.lock().unwrap();
+                assert_eq!(shown[0].app_name, "clink");
+                assert_eq!(shown[0].summary, "clink cleaned a link");
+                assert_eq!(shown[0].body, "Removed 2 tracking parameters.");
+                assert_eq!(shown[0].actions, ["undo", "Undo"]);
+                // The second one replaces the first.
+                assert_eq!(shown[1].id, 1);
+                assert!(
+                    shown[1]
+                        .body
+                        .ends_with("1 more rewrite since the last notification.")
+                );
+            }
+
+            let emitter =
+                SignalEmitter::new(server.inner(), "/org/freedesktop/Notifications").unwrap();
+            // A click on some other notification is ignored.
+            for (id, action) in [(7, UNDO_ACTION), (1, "default"), (1, UNDO_ACTION)] {
+                zbus::block_on(Server::action_invoked(&emitter, id, action)).unwrap();
+            }
+            let mut events = Vec::new();
+            wait_for("the undo click", || {
+                events.extend(notifier.events());
+                !events.is_empty()
+            });
+            assert_ne!(first, last);
+            assert_eq!(events, [Event::Undo(last)]);
+            thread::sleep(Duration::from_millis(100));
+            assert!(notifier.events().is_empty());
+        }
+
+        #[test]
+        #[ignore = "needs dbus-daemon on PATH, e.g. `cargo test -- --ignored`"]
+        fn a_missing_notification_server_is_reported_not_waited_for() {
+            let bus = private_bus();
+            let mut notifier = Notifier::start(
+                connection::Builder::address(bus.address.as_str()),
+                &Notifications {
+                    enabled: true,
+                    min_interval: 0,
+                },
+            )
+            .unwrap();
+            let start = Instant::now();
+            notifier.notify(&cleaned());
+            assert!(start.elapsed() < Duration::from_millis(100));
+            let mut events = Vec::new();
+            wait_for("the failure", || {
+                events.extend(notifier.events());
+                !events.is_empty()
+            });
+            assert!(matches!(&events[..], [Event::Failed(e)] if e.starts_with("Failed to show")));
+        }
+    }
+}
+
+// Without a freedesktop session bus there is nothing to notify;
+// `check_notifications` tells the user so.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub struct Notifier;
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+impl Notifier {
+    pub fn connect(_cfg: &Notifications) -> Result<Self, String> {
+        Err("notifications aren't available on this platform".to_string())
+    }
+
+    pub fn set_min_interval(&mut self, _seconds: u64) {}
+
+    pub fn notify(&mut self, _result: &CleanResult) -> u64 {
+        0
+    }
+
+    pub fn events(&mut self) -> Vec<Event> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_lets_one_through_per_interval() {
+        let mut limit = RateLimit::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(limit.remaining(start), Duration::ZERO);
+        assert!(limit.allow(start));
+        assert!(!limit.allow(start + Duration::from_secs(9)));
+        assert_eq!(
+            limit.remaining(start + Duration::from_secs(9)),
+            Duration::from_secs(1)
+        );
+        assert!(limit.allow(start + Duration::from_secs(10)));
+        let mut every = RateLimit::new(Duration::ZERO);
+        assert!(every.allow(start));
+        assert!(every.allow(start));
+    }
+
+    #[test]
+    fn message_summarizes_the_rewrite() {
+        let result = CleanResult {
+            urls_cleaned: 2,
+            params_removed: 1,
+            exits_unwrapped: 3,
+            ..CleanResult::default()
+        };
+        assert_eq!(
+            message(&result, 0),
+            (
+                "clink cleaned 2 links".to_string(),
+                "Removed 1 tracking parameter and unwrapped 3 redirects.".to_string()
+            )
+        );
+        let canonicalized = CleanResult {
+            urls_cleaned: 1,
+            ..CleanResult::default()
+        };
+        assert_eq!(
+            message(&canonicalized, 4).1,
+            "Rewritten according to your rules.\n4 more rewrites since the last notification."
+        );
+    }
+
+    #[test]
+    fn notifications_are_off_by_default() {
+        let cfg: crate::config::ClinkConfig = toml::from_str(
+            "mode = 'remove'\nreplace_to = 'x'\nsleep_duration = 1\n[providers]\n\
+             [notifications]\nenabled = true\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.notifications.to_string(), "on, at most one every 10s");
+        assert_eq!(Notifications::default().to_string(), "off");
+    }
+}
@@ -1,7 +1,11 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
+use crate::mode::Mode;
 use percent_encoding::percent_decode_str;
 use regex::Regex;
+use regex_syntax::hir::{Class, Hir, HirKind};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -15,20 +19,99 @@ pub struct ProviderConfig {
     pub redirections: Vec<String>,
     #[serde(default)]
     pub exceptions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub raw_rules: Vec<String>,
+    // ClearURLs `forceRedirection`: unwrap even when the redirect param isn't
+    // where the redirection regex expects it (e.g. `?src=x&u=...` against an
+    // anchored `\?u=`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_redirection: Option<bool>,
+    // ClearURLs `completeProvider`: the whole domain is an ad or tracking
+    // endpoint. What happens to such links is `complete_provider_action`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complete_provider: Option<bool>,
+    // Overrides the top-level `mode` / `replace_to` for URLs this provider
+    // matches. See `Clink::resolve_mode` for how competing overrides resolve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Mode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replace_to: Option<String>,
+    // Allowlist: when set, only these params survive on matching URLs and
+    // every other param is treated as tracking. Entries follow the same
+    // literal/regex syntax as `rules`. An empty list keeps nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<Vec<String>>,
+    // Params that must never be stripped on matching URLs (every URL for
+    // `global`), whichever rule — local, remote, or built-in — tracks them.
+    // Same literal/regex syntax as `rules`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unrules: Vec<String>,
+    // Higher runs first when several providers could unwrap the same URL.
+    // Unset falls back to the default for `source`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(skip)]
+    pub source: ProviderSource,
+}
+
+// Where a provider was defined. A provider present locally stays `Local`
+// after remote rules merge into it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ProviderSource {
+    #[default]
+    Local,
+    Remote,
+    Builtin,
+}
+
+impl ProviderSource {
+    pub fn default_priority(self) -> i32 {
+        match self {
+            ProviderSource::Local => 100,
+            ProviderSource::Remote => 50,
+            ProviderSource::Builtin => 0,
+        }
+    }
 }
 
 impl ProviderConfig {
     // Merge another provider into this one, preserving local precedence for
-    // url_pattern (only fills it when absent locally) and deduplicating rules,
-    // redirections, and exceptions so a shared entry between local and remote
-    // doesn't bloat the compiled provider's regex/literal sets.
+    // url_pattern, force_redirection, complete_provider, mode, replace_to,
+    // keep, and priority (only filled when absent locally) and deduplicating rules, redirections, exceptions, raw
+    // rules, and unrules so a shared entry between local and remote doesn't
+    // bloat the compiled provider's regex/literal sets.
     pub fn merge_from(&mut self, other: &Self) {
         extend_unique(&mut self.rules, &other.rules);
         extend_unique(&mut self.redirections, &other.redirections);
         extend_unique(&mut self.exceptions, &other.exceptions);
+        extend_unique(&mut self.raw_rules, &other.raw_rules);
+        extend_unique(&mut self.unrules, &other.unrules);
         if self.url_pattern.is_none() {
             self.url_pattern.clone_from(&other.url_pattern);
         }
+        if self.force_redirection.is_none() {
+            self.force_redirection = other.force_redirection;
+        }
+        if self.complete_provider.is_none() {
+            self.complete_provider = other.complete_provider;
+        }
+        if self.mode.is_none() {
+            self.mode = other.mode;
+        }
+        if self.replace_to.is_none() {
+            self.replace_to.clone_from(&other.replace_to);
+        }
+        if self.keep.is_none() {
+            self.keep.clone_from(&other.keep);
+        }
+        if self.priority.is_none() {
+            self.priority = other.priority;
+        }
+    }
+
+    pub fn effective_priority(&self) -> i32 {
+        self.priority
+            .unwrap_or_else(|| self.source.default_priority())
     }
 }
 
@@ -42,9 +125,39 @@ fn extend_unique(target: &mut Vec<String>, source: &[String]) {
 
 pub struct CompiledRules {
     literals: HashSet<String>,
-    patterns: Vec<Regex>,
+    patterns: Vec<(String, Regex)>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    Literal,
+    Regex,
+    // The param is missing from a matching provider's `keep` list.
+    Allowlist,
+}
+
+impl fmt::Display for RuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RuleKind::Literal => write!(f, "literal"),
+            RuleKind::Regex => write!(f, "regex"),
+            RuleKind::Allowlist => write!(f, "allowlist"),
+        }
+    }
+}
+
+// The rule that claimed a parameter, as written in the config (literals are
+// stored lowercased). Used by `clink explain` to answer "why did this go?".
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch<'a> {
+    pub rule: &'a str,
+    pub kind: RuleKind,
 }
 
+// The provider whose rules apply to every URL. It has no url_pattern.
+pub const GLOBAL_PROVIDER: &str = "global";
+
 const REGEX_CHARS: &[char] = &[
     '[', ']', '(', ')', '{', '}', '*', '+', '?', '\\', '|', '^', '$',
 ];
@@ -70,7 +183,7 @@ impl CompiledRules {
         for rule in rules {
             if rule.contains(REGEX_CHARS) {
                 if let Ok(re) = Regex::new(&wrap_rule(rule)) {
-                    patterns.push(re);
+                    patterns.push((rule.clone(), re));
                 }
             } else {
                 literals.insert(rule.to_lowercase());
@@ -81,15 +194,72 @@ impl CompiledRules {
     }
 
     pub fn is_tracked(&self, param: &str) -> bool {
+        self.matching_rule(param).is_some()
+    }
+
+    pub fn matching_rule(&self, param: &str) -> Option<RuleMatch<'_>> {
         let lower = param.to_lowercase();
-        self.literals.contains(&lower) || self.patterns.iter().any(|re| re.is_match(param))
+        if let Some(literal) = self.literals.get(&lower) {
+            return Some(RuleMatch {
+                rule: literal,
+                kind: RuleKind::Literal,
+            });
+        }
+        self.patterns
+            .iter()
+            .find(|(_, re)| re.is_match(param))
+            .map(|(rule, _)| RuleMatch {
+                rule,
+                kind: RuleKind::Regex,
+            })
+    }
+}
+
+// ClearURLs `rawRules`: regexes removed from the whole URL string, for
+// tracking that lives outside the query (e.g. Amazon's `/ref=...` path
+// segment). Applied case-insensitively and to every match, like the addon's
+// `gi` flags.
+pub struct RawRules {
+    patterns: Vec<Regex>,
+}
+
+impl RawRules {
+    pub fn new(rules: &[String]) -> Self {
+        let patterns = rules
+            .iter()
+            .filter_map(|r| Regex::new(&case_insensitive(r)).ok())
+            .collect();
+        Self { patterns }
+    }
+
+    // Returns the stripped URL and the rules that removed something, as the
+    // user wrote them. An empty list means the URL is unchanged.
+    pub fn strip<'a>(&'a self, url: &str) -> (String, Vec<&'a str>) {
+        let mut current = url.to_string();
+        let mut fired = Vec::new();
+        for re in &self.patterns {
+            if let Cow::Owned(stripped) = re.replace_all(&current, "") {
+                current = stripped;
+                fired.push(re.as_str().strip_prefix("(?i)").unwrap_or(re.as_str()));
+            }
+        }
+        (current, fired)
     }
 }
 
 pub struct CompiledProvider {
-    url_pattern: Regex,
+    pub name: String,
+    pub priority: i32,
+    url_pattern: Option<Regex>,
     pub rules: CompiledRules,
+    pub raw_rules: RawRules,
+    pub complete: bool,
+    pub mode: Option<Mode>,
+    pub replace_to: Option<String>,
+    pub keep: Option<CompiledRules>,
+    pub unrules: CompiledRules,
     redirections: Vec<Regex>,
+    force_redirection: bool,
     exceptions: Vec<Regex>,
 }
 
@@ -111,6 +281,13 @@ pub fn check_provider(name: &str, config: &ProviderConfig) -> Vec<String> {
             }
         }
     }
+    for rule in config.unrules.iter().filter(|r| r.contains(REGEX_CHARS)) {
+        if let Err(e) = Regex::new(&wrap_rule(rule)) {
+            warnings.push(format!(
+                "[providers.{name}] unrule '{rule}' failed to compile: {e}"
+            ));
+        }
+    }
     for redir in &config.redirections {
         if let Err(e) = Regex::new(&case_insensitive(redir)) {
             warnings.push(format!(
@@ -125,15 +302,208 @@ pub fn check_provider(name: &str, config: &ProviderConfig) -> Vec<String> {
             ));
         }
     }
+    for raw in &config.raw_rules {
+        if let Err(e) = Regex::new(&case_insensitive(raw)) {
+            warnings.push(format!(
+                "[providers.{name}] raw rule '{raw}' failed to compile: {e}"
+            ));
+        }
+    }
+    if config.force_redirection == Some(true) && config.redirections.is_empty() {
+        warnings.push(format!(
+            "[providers.{name}] force_redirection is set but there are no redirections to force"
+        ));
+    }
+    if config.replace_to.is_some() && config.mode.is_some_and(|m| m != Mode::Replace) {
+        warnings.push(format!(
+            "[providers.{name}] replace_to has no effect because mode is not 'replace'"
+        ));
+    }
+    if let Some(keep) = &config.keep {
+        if keep.is_empty() && name != GLOBAL_PROVIDER {
+            warnings.push(format!(
+                "[providers.{name}] keep is empty, so every param is removed from matching URLs"
+            ));
+        }
+        for rule in keep.iter().filter(|r| r.contains(REGEX_CHARS)) {
+            if let Err(e) = Regex::new(&wrap_rule(rule)) {
+                warnings.push(format!(
+                    "[providers.{name}] keep entry '{rule}' failed to compile: {e}"
+                ));
+            }
+        }
+    }
+    check_scope(name, config, &mut warnings);
     warnings
 }
 
+// Pairs of providers that share a priority yet can both unwrap the same URL,
+// so only their names decide which one runs. Overlap is probed with a sample
+// URL generated from each redirection regex — a heuristic that can miss an
+// overlap but never reports one that isn't there.
+pub fn check_redirect_overlaps(providers: &HashMap<String, ProviderConfig>) -> Vec<String> {
+    let compiled: Vec<CompiledProvider> = providers
+        .iter()
+        .filter(|(_, cfg)| !cfg.redirections.is_empty())
+        .filter_map(|(name, cfg)| CompiledProvider::new(name, cfg))
+        .collect();
+
+    let mut overlaps: Vec<(&str, &str, String)> = Vec::new();
+    for a in &compiled {
+        for sample in a
+            .redirections
+            .iter()
+            .filter_map(|re| regex_sample(re.as_str()))
+        {
+            if a.try_redirect(&sample).is_none() || !a.matches_url(&sample) {
+                continue;
+            }
+            for b in &compiled {
+                if b.name == a.name || b.priority != a.priority {
+                    continue;
+                }
+                if !b.matches_url(&sample) || b.try_redirect(&sample).is_none() {
+                    continue;
+                }
+                let pair = if a.name < b.name {
+                    (a.name.as_str(), b.name.as_str())
+                } else {
+                    (b.name.as_str(), a.name.as_str())
+                };
+                if !overlaps.iter().any(|(x, y, _)| (*x, *y) == pair) {
+                    overlaps.push((pair.0, pair.1, sample.clone()));
+                }
+            }
+        }
+    }
+    overlaps.sort();
+    overlaps
+        .into_iter()
+        .map(|(a, b, sample)| {
+            format!(
+                "[providers.{a}] and [providers.{b}] can both unwrap URLs like '{sample}' at equal priority; set `priority` on one of them to make the order explicit"
+            )
+        })
+        .collect()
+}
+
+// Builds one string the pattern matches: first alternative, minimum
+// repetitions, and a plain alphanumeric from each class where possible so the
+// result reads like a URL. Anchors and word boundaries emit nothing.
+fn regex_sample(pattern: &str) -> Option<String> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+    let mut out = String::new();
+    write_sample(&hir, &mut out);
+    Some(out)
+}
+
+fn write_sample(hir: &Hir, out: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(lit) => out.push_str(&String::from_utf8_lossy(&lit.0)),
+        HirKind::Class(class) => {
+            if let Some(c) = class_sample(class) {
+                out.push(c);
+            }
+        }
+        HirKind::Repetition(rep) => {
+            for _ in 0..rep.min {
+                write_sample(&rep.sub, out);
+            }
+        }
+        HirKind::Capture(cap) => write_sample(&cap.sub, out),
+        HirKind::Concat(subs) => subs.iter().for_each(|sub| write_sample(sub, out)),
+        HirKind::Alternation(subs) => {
+            if let Some(first) = subs.first() {
+                write_sample(first, out);
+            }
+        }
+    }
+}
+
+fn class_sample(class: &Class) -> Option<char> {
+    const PREFERRED: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+    match class {
+        Class::Unicode(cls) => PREFERRED
+            .chars()
+            .find(|c| {
+                cls.ranges()
+                    .iter()
+                    .any(|r| (r.start()..=r.end()).contains(c))
+            })
+            .or_else(|| {
+                cls.ranges()
+                    .first()
+                    .map(regex_syntax::hir::ClassUnicodeRange::start)
+            }),
+        Class::Bytes(cls) => PREFERRED
+            .bytes()
+            .find(|b| {
+                cls.ranges()
+                    .iter()
+                    .any(|r| (r.start()..=r.end()).contains(b))
+            })
+            .or_else(|| {
+                cls.ranges()
+                    .first()
+                    .map(regex_syntax::hir::ClassBytesRange::start)
+            })
+            .map(char::from),
+    }
+}
+
+// The global provider applies everywhere and ignores per-URL settings; any
+// other provider without a url_pattern is never compiled at all.
+fn check_scope(name: &str, config: &ProviderConfig, warnings: &mut Vec<String>) {
+    if name != GLOBAL_PROVIDER {
+        if config.url_pattern.is_none() {
+            warnings.push(format!(
+                "[providers.{name}] has no url_pattern, so it never matches; only [providers.{GLOBAL_PROVIDER}] applies to every URL"
+            ));
+        }
+        return;
+    }
+    let ignored: Vec<&str> = [
+        ("url_pattern", config.url_pattern.is_some()),
+        ("mode", config.mode.is_some()),
+        ("replace_to", config.replace_to.is_some()),
+        ("keep", config.keep.is_some()),
+        ("complete_provider", config.complete_provider.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(field, set)| set.then_some(field))
+    .collect();
+    if !ignored.is_empty() {
+        warnings.push(format!(
+            "[providers.{name}] ignores {} because it applies to every URL; use the top-level settings instead",
+            ignored.join(", ")
+        ));
+    }
+}
+
 impl CompiledProvider {
-    pub fn new(config: &ProviderConfig) -> Option<Self> {
+    pub fn new(name: &str, config: &ProviderConfig) -> Option<Self> {
         let pattern_str = config.url_pattern.as_ref()?;
         let url_pattern = Regex::new(&case_insensitive(pattern_str)).ok()?;
+        Some(Self::compile(name, config, Some(url_pattern)))
+    }
+
+    // The `global` provider applies to every URL that none of its exceptions
+    // match. Per-URL overrides (mode, keep, complete_provider) only make
+    // sense for scoped providers, so they are dropped here;
+    // `check_provider` warns about them.
+    pub fn global(config: &ProviderConfig) -> Self {
+        let mut provider = Self::compile(GLOBAL_PROVIDER, config, None);
+        provider.complete = false;
+        provider.mode = None;
+        provider.replace_to = None;
+        provider.keep = None;
+        provider
+    }
 
+    fn compile(name: &str, config: &ProviderConfig, url_pattern: Option<Regex>) -> Self {
         let rules = CompiledRules::new(&config.rules);
+        let raw_rules = RawRules::new(&config.raw_rules);
 
         let redirections = config
             .redirections
@@ -147,31 +517,83 @@ impl CompiledProvider {
             .filter_map(|r| Regex::new(&case_insensitive(r)).ok())
             .collect();
 
-        Some(Self {
+        Self {
+            name: name.to_string(),
+            priority: config.effective_priority(),
             url_pattern,
             rules,
+            raw_rules,
+            complete: config.complete_provider == Some(true),
+            mode: config.mode,
+            replace_to: config.replace_to.clone(),
+            keep: config.keep.as_deref().map(CompiledRules::new),
+            unrules: CompiledRules::new(&config.unrules),
             redirections,
+            force_redirection: config.force_redirection == Some(true),
             exceptions,
-        })
+        }
     }
 
     pub fn matches_url(&self, url: &str) -> bool {
-        self.url_pattern.is_match(url) && !self.exceptions.iter().any(|re| re.is_match(url))
+        self.matches_url_pattern(url) && self.matching_exception(url).is_none()
+    }
+
+    pub fn matches_url_pattern(&self, url: &str) -> bool {
+        self.url_pattern.as_ref().is_none_or(|re| re.is_match(url))
+    }
+
+    // Returns the exception as the user wrote it, without the `(?i)` prefix
+    // added at compile time.
+    pub fn matching_exception(&self, url: &str) -> Option<&str> {
+        self.exceptions
+            .iter()
+            .find(|re| re.is_match(url))
+            .map(|re| re.as_str().strip_prefix("(?i)").unwrap_or(re.as_str()))
     }
 
     pub fn try_redirect(&self, url: &str) -> Option<String> {
-        for re in &self.redirections {
-            if let Some(caps) = re.captures(url) {
-                if let Some(m) = caps.get(1) {
-                    return Some(
-                        percent_decode_str(m.as_str())
-                            .decode_utf8_lossy()
-                            .into_owned(),
-                    );
-                }
-            }
+        if let Some(dest) = self.capture_redirect(url) {
+            return Some(dest);
         }
-        None
+        if !self.force_redirection {
+            return None;
+        }
+        // Upstream redirection regexes are often anchored on the first
+        // param (`/l.php\?u=`). Retry with each query segment moved to the
+        // front so extra params ahead of the target don't defeat the unwrap.
+        let (base, query) = url.split_once('?')?;
+        let (query, fragment) = match query.split_once('#') {
+            Some((q, f)) => (q, Some(f)),
+            None => (query, None),
+        };
+        let segments: Vec<&str> = query.split('&').collect();
+        (1..segments.len()).find_map(|i| {
+            let mut reordered = vec![segments[i]];
+            reordered.extend(
+                segments
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, s)| *s),
+            );
+            let mut candidate = format!("{base}?{}", reordered.join("&"));
+            if let Some(fragment) = fragment {
+                candidate.push('#');
+                candidate.push_str(fragment);
+            }
+            self.capture_redirect(&candidate)
+        })
+    }
+
+    fn capture_redirect(&self, url: &str) -> Option<String> {
+        self.redirections.iter().find_map(|re| {
+            let m = re.captures(url)?.get(1)?;
+            Some(
+                percent_decode_str(m.as_str())
+                    .decode_utf8_lossy()
+                    .into_owned(),
+            )
+        })
     }
 }
 
@@ -212,6 +634,7 @@ rule = ["fbclid"]
                 rules: vec!["utm_source".to_string(), "fbclid".to_string()],
                 redirections: vec![],
                 exceptions: vec![],
+                ..Default::default()
             },
         );
 
@@ -232,6 +655,7 @@ rule = ["fbclid"]
             rules: vec![],
             redirections: vec![r"url=([^&]+)".to_string()],
             exceptions: vec![],
+            ..Default::default()
         };
 
         let mut providers = HashMap::new();
@@ -320,6 +744,26 @@ rules = ["fbclid", "gclid"]
         );
     }
 
+    #[test]
+    fn compiled_rules_reports_matching_rule() {
+        let rules = CompiledRules::new(&["FBCLID".to_string(), "utm_[a-z]+".to_string()]);
+        assert_eq!(
+            rules.matching_rule("Fbclid"),
+            Some(RuleMatch {
+                rule: "fbclid",
+                kind: RuleKind::Literal
+            })
+        );
+        assert_eq!(
+            rules.matching_rule("utm_source"),
+            Some(RuleMatch {
+                rule: "utm_[a-z]+",
+                kind: RuleKind::Regex
+            })
+        );
+        assert_eq!(rules.matching_rule("page"), None);
+    }
+
     #[test]
     fn compiled_rules_invalid_regex_skipped() {
         let rules = CompiledRules::new(&["[invalid".to_string(), "fbclid".to_string()]);
@@ -336,9 +780,10 @@ rules = ["fbclid", "gclid"]
             rules: vec!["si".to_string()],
             redirections: vec![],
             exceptions: vec![],
+            ..Default::default()
         };
 
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
 
         assert!(provider.matches_url("https://www.youtube.com/watch?v=abc"));
         assert!(provider.matches_url("https://youtu.be/abc"));
@@ -352,9 +797,10 @@ rules = ["fbclid", "gclid"]
             rules: vec![],
             redirections: vec![r"url=([^&]+)".to_string()],
             exceptions: vec![],
+            ..Default::default()
         };
 
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         let result = provider
             .try_redirect("https://exit.sc/?url=https%3A%2F%2Fexample.com%2Fpage%3Fid%3D1")
             .unwrap();
@@ -369,14 +815,263 @@ rules = ["fbclid", "gclid"]
             rules: vec![],
             redirections: vec![r"url=([^&]+)".to_string()],
             exceptions: vec![],
+            ..Default::default()
         };
 
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         let result = provider.try_redirect("https://exit.sc/?other=value");
 
         assert!(result.is_none());
     }
 
+    #[test]
+    fn force_redirection_unwraps_past_leading_params() {
+        let mut config = ProviderConfig {
+            url_pattern: Some(r"^https?://l\.facebook\.com".to_string()),
+            redirections: vec![r"^https?://l\.facebook\.com/l\.php\?u=([^&#]+)".to_string()],
+            ..Default::default()
+        };
+        let url = "https://l.facebook.com/l.php?h=AT0&u=https%3A%2F%2Fexample.com%2F&s=1#frag";
+
+        let provider = CompiledProvider::new("fb", &config).unwrap();
+        assert!(
+            provider.try_redirect(url).is_none(),
+            "without force_redirection the anchored regex must not match"
+        );
+
+        config.force_redirection = Some(true);
+        let provider = CompiledProvider::new("fb", &config).unwrap();
+        assert_eq!(
+            provider.try_redirect(url).as_deref(),
+            Some("https://example.com/")
+        );
+        assert!(
+            provider
+                .try_redirect("https://l.facebook.com/l.php?h=AT0")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn check_provider_flags_force_redirection_without_redirections() {
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://x\.com".to_string()),
+            force_redirection: Some(true),
+            ..Default::default()
+        };
+        let warnings = check_provider("scoped", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("force_redirection"));
+    }
+
+    #[test]
+    fn check_provider_flags_scoped_provider_without_url_pattern() {
+        let config = ProviderConfig {
+            rules: vec!["ref".to_string()],
+            complete_provider: Some(true),
+            ..Default::default()
+        };
+        let warnings = check_provider("ads", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("has no url_pattern"));
+    }
+
+    #[test]
+    fn check_provider_accepts_full_global_provider() {
+        let config = ProviderConfig {
+            rules: vec!["fbclid".to_string()],
+            exceptions: vec![r"^https?://[^/]+/admin".to_string()],
+            redirections: vec![r"[?&]redirect_to=([^&]+)".to_string()],
+            raw_rules: vec![";jsessionid=[^?#]*".to_string()],
+            unrules: vec!["t".to_string()],
+            ..Default::default()
+        };
+        assert!(check_provider("global", &config).is_empty());
+
+        let config = ProviderConfig {
+            url_pattern: Some(".*".to_string()),
+            keep: Some(vec![]),
+            ..Default::default()
+        };
+        let warnings = check_provider("global", &config);
+        assert_eq!(
+            warnings,
+            vec![
+                "[providers.global] ignores url_pattern, keep because it applies to every URL; use the top-level settings instead"
+            ]
+        );
+    }
+
+    #[test]
+    fn check_provider_flags_mode_override_mistakes() {
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://x\.com".to_string()),
+            mode: Some(Mode::Remove),
+            replace_to: Some("x".to_string()),
+            ..Default::default()
+        };
+        let warnings = check_provider("scoped", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("replace_to has no effect"));
+
+        let global = ProviderConfig {
+            mode: Some(Mode::Replace),
+            ..Default::default()
+        };
+        let warnings = check_provider("global", &global);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("ignores mode"));
+    }
+
+    #[test]
+    fn merge_from_keeps_local_mode_override() {
+        let mut local = ProviderConfig {
+            mode: Some(Mode::Off),
+            ..Default::default()
+        };
+        local.merge_from(&ProviderConfig {
+            mode: Some(Mode::Remove),
+            replace_to: Some("remote".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(local.mode, Some(Mode::Off));
+        assert_eq!(local.replace_to.as_deref(), Some("remote"));
+    }
+
+    #[test]
+    fn merge_from_lets_local_switch_off_clearurls_flags() {
+        let mut local = ProviderConfig {
+            force_redirection: Some(false),
+            complete_provider: Some(false),
+            ..Default::default()
+        };
+        let remote = ProviderConfig {
+            redirections: vec!["u=([^&]+)".to_string()],
+            force_redirection: Some(true),
+            complete_provider: Some(true),
+            ..Default::default()
+        };
+        local.merge_from(&remote);
+        assert_eq!(local.force_redirection, Some(false));
+        assert_eq!(local.complete_provider, Some(false));
+
+        let mut unset = ProviderConfig::default();
+        unset.merge_from(&remote);
+        assert_eq!(unset.force_redirection, Some(true));
+        assert_eq!(unset.complete_provider, Some(true));
+    }
+
+    #[test]
+    fn mode_override_deserializes() {
+        let config: ProviderConfig =
+            toml::from_str("url_pattern = 'x'\nmode = 'replace'\nreplace_to = 'y'\n").unwrap();
+        assert_eq!(config.mode, Some(Mode::Replace));
+        assert_eq!(config.replace_to.as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn check_provider_warns_on_empty_keep() {
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://x\.com".to_string()),
+            keep: Some(vec![]),
+            ..Default::default()
+        };
+        let warnings = check_provider("scoped", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("keep is empty"));
+
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://x\.com".to_string()),
+            keep: Some(vec!["v".to_string(), "(bad".to_string()]),
+            ..Default::default()
+        };
+        let warnings = check_provider("scoped", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("keep entry '(bad'"));
+    }
+
+    #[test]
+    fn check_provider_flags_bad_unrule() {
+        let config = ProviderConfig {
+            unrules: vec!["t".to_string(), "utm_(".to_string()],
+            ..Default::default()
+        };
+        let warnings = check_provider("global", &config);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("unrule 'utm_('"));
+    }
+
+    #[test]
+    fn keep_distinguishes_absent_from_empty() {
+        let absent: ProviderConfig = toml::from_str("url_pattern = 'x'").unwrap();
+        assert!(absent.keep.is_none());
+        let empty: ProviderConfig = toml::from_str("url_pattern = 'x'\nkeep = []").unwrap();
+        assert_eq!(empty.keep, Some(vec![]));
+    }
+
+    #[test]
+    fn effective_priority_defaults_by_source() {
+        let mut config = ProviderConfig::default();
+        assert_eq!(config.effective_priority(), 100);
+        config.source = ProviderSource::Remote;
+        assert_eq!(config.effective_priority(), 50);
+        config.source = ProviderSource::Builtin;
+        assert_eq!(config.effective_priority(), 0);
+        config.priority = Some(-5);
+        assert_eq!(config.effective_priority(), -5);
+    }
+
+    #[test]
+    fn regex_sample_builds_a_matching_url() {
+        let pattern = r"(?i)^https?://[a-z0-9.-]*google\.[a-z.]+/url\?.*?(?:url|q)=([^&]+)";
+        let sample = regex_sample(pattern).unwrap();
+        assert_eq!(sample, "http://google.a/url?url=a");
+        assert!(Regex::new(pattern).unwrap().is_match(&sample));
+    }
+
+    fn redirector(pattern: &str, priority: Option<i32>) -> ProviderConfig {
+        ProviderConfig {
+            url_pattern: Some(r"^https?://go\.test".to_string()),
+            redirections: vec![pattern.to_string()],
+            priority,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_redirect_overlaps_flags_equal_priority() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "first".to_string(),
+            redirector(r"^https?://go\.test/\?.*?to=([^&]+)", None),
+        );
+        providers.insert("second".to_string(), redirector(r"to=([^&]+)", None));
+        providers.insert(
+            "unrelated".to_string(),
+            redirector(r"^https?://go\.test/out/([^?]+)", None),
+        );
+        let warnings = check_redirect_overlaps(&providers);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(
+            warnings[0].starts_with("[providers.first] and [providers.second]"),
+            "{warnings:?}"
+        );
+
+        providers.get_mut("second").unwrap().priority = Some(150);
+        assert!(check_redirect_overlaps(&providers).is_empty());
+    }
+
+    #[test]
+    fn clearurls_flags_are_omitted_when_unset() {
+        let toml_str = toml::to_string(&ProviderConfig {
+            rules: vec!["fbclid".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!toml_str.contains("force_redirection"), "{toml_str}");
+        assert!(!toml_str.contains("complete_provider"), "{toml_str}");
+    }
+
     #[test]
     fn compiled_provider_respects_exceptions() {
         let config = ProviderConfig {
@@ -384,8 +1079,9 @@ rules = ["fbclid", "gclid"]
             rules: vec!["utm_source".into()],
             redirections: vec![],
             exceptions: vec![r"^https?://youtube\.com/redirect".into()],
+            ..Default::default()
         };
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         assert!(provider.matches_url("https://youtube.com/watch?v=abc"));
         assert!(
             !provider.matches_url("https://youtube.com/redirect?q=abc"),
@@ -393,6 +1089,26 @@ rules = ["fbclid", "gclid"]
         );
     }
 
+    #[test]
+    fn compiled_provider_reports_matching_exception() {
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://youtube\.com".into()),
+            exceptions: vec![r"^https?://youtube\.com/redirect".into()],
+            ..Default::default()
+        };
+        let provider = CompiledProvider::new("youtube", &config).unwrap();
+        assert_eq!(provider.name, "youtube");
+        assert!(provider.matches_url_pattern("https://youtube.com/redirect?q=abc"));
+        assert_eq!(
+            provider.matching_exception("https://youtube.com/redirect?q=abc"),
+            Some(r"^https?://youtube\.com/redirect")
+        );
+        assert_eq!(
+            provider.matching_exception("https://youtube.com/watch?v=abc"),
+            None
+        );
+    }
+
     #[test]
     fn compiled_provider_skips_redirect_when_excepted() {
         let config = ProviderConfig {
@@ -400,8 +1116,9 @@ rules = ["fbclid", "gclid"]
             rules: vec![],
             redirections: vec![r"url=([^&]+)".into()],
             exceptions: vec![r"^https?://exit\.sc/admin".into()],
+            ..Default::default()
         };
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         assert!(
             !provider.matches_url("https://exit.sc/admin/?url=https%3A%2F%2Fbar.com"),
             "excepted URL must not be treated as matching"
@@ -414,7 +1131,7 @@ rules = ["fbclid", "gclid"]
             url_pattern: Some(r"^https?://youtube\.com".to_string()),
             ..Default::default()
         };
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         assert!(
             provider.matches_url("https://YouTube.com/watch"),
             "url_pattern must match host case-insensitively"
@@ -428,7 +1145,7 @@ rules = ["fbclid", "gclid"]
             redirections: vec![r"url=([^&]+)".to_string()],
             ..Default::default()
         };
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         let result = provider
             .try_redirect("https://exit.sc/?URL=https%3A%2F%2Fexample.com")
             .expect("uppercase URL= should still capture");
@@ -445,7 +1162,7 @@ rules = ["fbclid", "gclid"]
             exceptions: vec![r"^https?://exit\.sc/admin".to_string()],
             ..Default::default()
         };
-        let provider = CompiledProvider::new(&config).unwrap();
+        let provider = CompiledProvider::new("test", &config).unwrap();
         assert!(
             !provider.matches_url("https://exit.sc/ADMIN/?url=foo"),
             "exception must apply case-insensitively"
@@ -459,9 +1176,10 @@ rules = ["fbclid", "gclid"]
             rules: vec!["fbclid".to_string()],
             redirections: vec![],
             exceptions: vec![],
+            ..Default::default()
         };
 
-        assert!(CompiledProvider::new(&config).is_none());
+        assert!(CompiledProvider::new("test", &config).is_none());
     }
 
     #[test]
@@ -471,6 +1189,7 @@ rules = ["fbclid", "gclid"]
             rules: vec!["fbclid".to_string(), "^utm_".to_string()],
             redirections: vec![r"url=([^&]+)".to_string()],
             exceptions: vec![],
+            ..Default::default()
         };
         assert!(check_provider("test", &config).is_empty());
     }
@@ -482,6 +1201,7 @@ rules = ["fbclid", "gclid"]
             rules: vec!["fbclid".to_string()],
             redirections: vec![],
             exceptions: vec![],
+            ..Default::default()
         };
         assert!(check_provider("global", &config).is_empty());
     }
@@ -555,6 +1275,47 @@ rules = ["fbclid", "gclid"]
         assert!(warnings[0].contains("[bad"));
     }
 
+    #[test]
+    fn check_provider_flags_bad_raw_rule() {
+        let config = ProviderConfig {
+            url_pattern: Some(r"^https?://x\.com".to_string()),
+            raw_rules: vec!["/ref=(".to_string()],
+            ..Default::default()
+        };
+        let warnings = check_provider("scoped", &config);
+        assert_eq!(
+            warnings.len(),
+            1,
+            "expected a single warning, got {warnings:?}"
+        );
+        assert!(warnings[0].contains("raw rule '/ref=('"));
+    }
+
+    #[test]
+    fn raw_rules_strip_every_match_and_report_rules() {
+        let raw = RawRules::new(&["/ref=[^/?]*".to_string(), "unused".to_string()]);
+        let (stripped, fired) = raw.strip("https://x.com/a/REF=1/b/ref=2?q=1");
+        assert_eq!(stripped, "https://x.com/a/b?q=1");
+        assert_eq!(fired, vec!["/ref=[^/?]*"]);
+
+        let (unchanged, fired) = raw.strip("https://x.com/a");
+        assert_eq!(unchanged, "https://x.com/a");
+        assert!(fired.is_empty());
+    }
+
+    #[test]
+    fn merge_from_dedupes_raw_rules() {
+        let mut local = ProviderConfig {
+            raw_rules: vec!["/ref=[^/?]*".to_string()],
+            ..Default::default()
+        };
+        local.merge_from(&ProviderConfig {
+            raw_rules: vec!["/ref=[^/?]*".to_string(), "/gp/x".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(local.raw_rules, vec!["/ref=[^/?]*", "/gp/x"]);
+    }
+
     #[test]
     fn check_provider_skips_literal_rules() {
         // Literal rules never go through Regex::new, so they can't fail.
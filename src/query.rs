@@ -0,0 +1,117 @@
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+
+const QUERY_COMPONENT_KEY: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'&').add(b'=').add(b'+');
+const QUERY_COMPONENT_VALUE: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'&').add(b'+');
+
+// One `key=value` segment of a query string. Rules match on the decoded
+// `key`, but the segment is written back from the bytes it was copied with
+// (`+` vs `%20`, `%2F` vs `/`, `key` vs `key=`), so only the params clink
+// actually rewrites get re-encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryParam {
+    pub key: String,
+    pub value: String,
+    raw_key: String,
+    // None for a bare `key` without `=`.
+    raw_value: Option<String>,
+}
+
+impl QueryParam {
+    pub fn new(key: &str, value: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.to_string(),
+            raw_key: utf8_percent_encode(key, QUERY_COMPONENT_KEY).to_string(),
+            raw_value: Some(utf8_percent_encode(value, QUERY_COMPONENT_VALUE).to_string()),
+        }
+    }
+
+    fn from_segment(segment: &str) -> Self {
+        let (raw_key, raw_value) = match segment.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (segment, None),
+        };
+        Self {
+            key: decode(raw_key),
+            value: raw_value.map(decode).unwrap_or_default(),
+            raw_key: raw_key.to_string(),
+            raw_value: raw_value.map(str::to_string),
+        }
+    }
+
+    // The key keeps its original bytes; only the value is re-encoded.
+    pub fn set_value(&mut self, value: String) {
+        self.raw_value = Some(utf8_percent_encode(&value, QUERY_COMPONENT_VALUE).to_string());
+        self.value = value;
+    }
+}
+
+// Empty segments (`a=1&&b=2`) carry no param and are dropped, matching how
+// `form_urlencoded` counts params.
+pub fn parse(query: &str) -> Vec<QueryParam> {
+    query
+        .split('&')
+        .filter(|segment| !segment.is_empty())
+        .map(QueryParam::from_segment)
+        .collect()
+}
+
+pub fn join(params: &[QueryParam]) -> String {
+    params
+        .iter()
+        .map(|p| match &p.raw_value {
+            Some(value) => format!("{}={value}", p.raw_key),
+            None => p.raw_key.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Same decoding as `form_urlencoded`, applied to one side of the `=` so
+// padding in values like `sig=abc==` survives.
+fn decode(raw: &str) -> String {
+    percent_decode_str(&raw.replace('+', " "))
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_original_bytes() {
+        for query in [
+            "q=a+b&path=%2Fdocs%2F&flag&empty=&x=/raw/",
+            "name=caf%C3%A9&sig=abc%3D%3D",
+        ] {
+            assert_eq!(join(&parse(query)), query);
+        }
+    }
+
+    #[test]
+    fn decodes_keys_and_values() {
+        let params = parse("utm%5Fsource=a+b&flag&sig=abc==");
+        assert_eq!(params[0].key, "utm_source");
+        assert_eq!(params[0].value, "a b");
+        assert_eq!(params[1].key, "flag");
+        assert_eq!(params[1].value, "");
+        assert_eq!(params[2].value, "abc==");
+    }
+
+    #[test]
+    fn rewrites_only_the_value() {
+        let mut params = parse("utm%5Fsource=x&q=a+b");
+        params[0].set_value("a b&c".to_string());
+        params.push(QueryParam::new("utm_source", "your_mom"));
+        assert_eq!(
+            join(&params),
+            "utm%5Fsource=a%20b%26c&q=a+b&utm_source=your_mom"
+        );
+    }
+
+    #[test]
+    fn drops_empty_segments() {
+        assert_eq!(join(&parse("&a=1&&b=2&")), "a=1&b=2");
+    }
+}
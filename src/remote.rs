@@ -4,6 +4,7 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 
 use crate::config::ClinkConfig;
+use crate::provider::{ProviderConfig, ProviderSource};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum RemoteFormat {
@@ -23,7 +24,7 @@ pub struct Remote {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct RemotePatterns {
-    pub providers: HashMap<String, crate::provider::ProviderConfig>,
+    pub providers: HashMap<String, ProviderConfig>,
 }
 
 pub fn resolve_patterns(config: &mut ClinkConfig, data_dir: &Path) -> Vec<String> {
@@ -71,24 +72,29 @@ pub fn resolve_patterns(config: &mut ClinkConfig, data_dir: &Path) -> Vec<String
         if config.verbose {
             eprintln!("using cached remote patterns");
         }
-        merge_patterns(config, &remote);
+        merge_patterns(config, &remote, ProviderSource::Remote);
     } else {
         if config.verbose {
             eprintln!("using built-in patterns");
         }
-        merge_patterns(config, crate::builtin::patterns());
+        merge_patterns(config, crate::builtin::patterns(), ProviderSource::Builtin);
     }
 
     warnings
 }
 
-fn merge_patterns(config: &mut ClinkConfig, source: &RemotePatterns) {
+// Providers that only exist upstream are tagged with where they came from
+// so their default priority ranks below the user's own providers.
+fn merge_patterns(config: &mut ClinkConfig, source: &RemotePatterns, origin: ProviderSource) {
     for (name, source_provider) in &source.providers {
         config
             .providers
             .entry(name.clone())
             .and_modify(|local| local.merge_from(source_provider))
-            .or_insert_with(|| source_provider.clone());
+            .or_insert_with(|| ProviderConfig {
+                source: origin,
+                ..source_provider.clone()
+            });
     }
 }
 
@@ -125,13 +131,13 @@ mod tests {
 
     #[test]
     fn test_config_without_remote_section() {
-        let toml_str = r#"
+        let toml_str = r"
 mode = 'remove'
 replace_to = 'clink'
 sleep_duration = 150
 
 [providers]
-"#;
+";
         let loaded: ClinkConfig = toml::from_str(toml_str).unwrap();
         assert!(loaded.remote.is_none());
     }
@@ -169,11 +175,11 @@ rules = ["fbclid"]
     // builtin while doing nothing they can see.
     #[test]
     fn remote_rejects_unknown_field() {
-        let toml_str = r#"
+        let toml_str = r"
 url = 'https://example.com'
 format = 'clearurls'
 extra = 'oops'
-"#;
+";
         let result = toml::from_str::<Remote>(toml_str);
         assert!(
             result.is_err(),
@@ -337,6 +343,12 @@ extra = 'oops'
             cfg.providers.contains_key("remote_only"),
             "should have remote-only provider"
         );
+        assert_eq!(
+            global.source,
+            ProviderSource::Local,
+            "a local provider stays local after remote rules merge in"
+        );
+        assert_eq!(cfg.providers["remote_only"].source, ProviderSource::Remote);
 
         let _ = std::fs::remove_dir_all(&dir);
     }
@@ -414,6 +426,11 @@ extra = 'oops'
             has_fbclid,
             "resolve_patterns without cache must fall back to builtin and populate fbclid"
         );
+        assert!(
+            cfg.providers
+                .values()
+                .all(|p| p.source == ProviderSource::Builtin)
+        );
 
         let _ = std::fs::remove_dir_all(&dir);
     }
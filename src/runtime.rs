@@ -2,6 +2,10 @@ use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
+
+use crate::config::Selection;
+
 // Write to a sibling `.tmp` file then `rename` over the target so a partial
 // write (Ctrl-C, OOM, power loss) can never leave a corrupt file where the
 // daemon expects valid content.
@@ -27,6 +31,20 @@ pub fn pid_file_path() -> PathBuf {
     runtime_dir().join("clink.pid")
 }
 
+pub fn status_file_path() -> PathBuf {
+    runtime_dir().join("clink.status.json")
+}
+
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join("clink.sock")
+}
+
+#[cfg(unix)]
+pub fn remove_socket() {
+    let _ = fs::remove_file(socket_path());
+}
+
 pub fn log_file_path() -> PathBuf {
     data_dir().join("clink.log")
 }
@@ -35,6 +53,10 @@ pub fn stats_file_path() -> PathBuf {
     data_dir().join("stats.toml")
 }
 
+pub fn history_file_path() -> PathBuf {
+    data_dir().join("history.jsonl")
+}
+
 fn runtime_dir() -> PathBuf {
     #[cfg(target_os = "macos")]
     {
@@ -116,6 +138,42 @@ pub fn remove_loaded_config() {
     let _ = fs::remove_file(loaded_config_path());
 }
 
+// What the running daemon reports about itself for `clink state`, both over
+// the control socket and in the status file. The file is written at startup
+// and whenever the status changes, and removed on shutdown.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct DaemonStatus {
+    #[serde(default)]
+    pub pid: u32,
+    #[serde(default)]
+    pub version: String,
+    pub backend: String,
+    #[serde(default)]
+    pub paused: bool,
+    // Local time a timed pause ends, `%Y-%m-%d %H:%M:%S`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_until: Option<String>,
+    #[serde(default)]
+    pub selections: Vec<Selection>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+pub fn write_status(status: &DaemonStatus) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(status)
+        .map_err(|e| format!("Failed to serialize status: {e}"))?;
+    write_atomic(&status_file_path(), &content)
+}
+
+pub fn read_status() -> Option<DaemonStatus> {
+    let content = fs::read_to_string(status_file_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn remove_status() {
+    let _ = fs::remove_file(status_file_path());
+}
+
 pub fn append_log(message: &str) -> Result<(), String> {
     let path = log_file_path();
     if let Some(parent) = path.parent() {
@@ -182,7 +240,7 @@ mod tests {
     #[test]
     fn test_is_running_dead_pid() {
         // PID 4194304 is above typical PID range
-        assert!(!is_running(4194304));
+        assert!(!is_running(4_194_304));
     }
 
     #[test]
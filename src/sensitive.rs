@@ -0,0 +1,68 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use regex::{Regex, RegexSet};
+
+// `sensitive_patterns` from the config. Content matching any of them is
+// treated like a copied password: the daemon leaves it alone without
+// cleaning, counting or logging it. Invalid patterns are dropped here and
+// reported by `ClinkConfig::validate`.
+pub struct SensitivePatterns {
+    set: RegexSet,
+}
+
+impl SensitivePatterns {
+    pub fn new(patterns: &[String]) -> Self {
+        let valid: Vec<&String> = patterns.iter().filter(|p| Regex::new(p).is_ok()).collect();
+        Self {
+            set: RegexSet::new(valid).unwrap_or_else(|_| RegexSet::empty()),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.set.is_match(text)
+    }
+}
+
+pub fn check_patterns(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .filter_map(|p| {
+            Regex::new(p)
+                .err()
+                .map(|e| format!("sensitive_patterns '{p}' failed to compile: {e}"))
+        })
+        .collect()
+}
+
+// Identifies skipped content between polls without keeping the secret
+// itself around in the daemon.
+pub fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_any_valid_pattern() {
+        let patterns = SensitivePatterns::new(&[
+            r"^\d{6}$".to_string(),
+            "(".to_string(),
+            r"^sk-[A-Za-z0-9]{20,}$".to_string(),
+        ]);
+        assert!(patterns.matches("123456"));
+        assert!(patterns.matches("sk-abcdefghijklmnopqrstuv"));
+        assert!(!patterns.matches("https://a.test/?utm_source=x"));
+        assert!(!SensitivePatterns::new(&[]).matches("123456"));
+    }
+
+    #[test]
+    fn reports_invalid_patterns() {
+        let warnings = check_patterns(&["ok".to_string(), "(".to_string()]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("sensitive_patterns '(' failed to compile"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::guard::Skip;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -8,6 +9,16 @@ pub struct Counters {
     pub params_removed: u32,
     pub exits_unwrapped: u32,
     pub clipboard_checks: u32,
+    // The share of `urls_cleaned` that came from the PRIMARY selection.
+    #[serde(default)]
+    pub primary_urls_cleaned: u32,
+    // Changes a `[guards]` check left alone, one counter per guard.
+    #[serde(default)]
+    pub skipped_too_large: u32,
+    #[serde(default)]
+    pub skipped_by_pattern: u32,
+    #[serde(default)]
+    pub skipped_not_single_url: u32,
 }
 
 impl Counters {
@@ -142,6 +153,34 @@ impl Stats {
         );
     }
 
+    pub fn increment_primary(&mut self, urls_cleaned: u32) {
+        for counters in [
+            &mut self.session.counters,
+            &mut self.today.counters,
+            &mut self.month.counters,
+            &mut self.year.counters,
+            &mut self.total,
+        ] {
+            counters.primary_urls_cleaned += urls_cleaned;
+        }
+    }
+
+    pub fn increment_skip(&mut self, skip: Skip) {
+        for counters in [
+            &mut self.session.counters,
+            &mut self.today.counters,
+            &mut self.month.counters,
+            &mut self.year.counters,
+            &mut self.total,
+        ] {
+            match skip {
+                Skip::TooLarge => counters.skipped_too_large += 1,
+                Skip::Pattern => counters.skipped_by_pattern += 1,
+                Skip::NotSingleUrl => counters.skipped_not_single_url += 1,
+            }
+        }
+    }
+
     pub fn check_rollovers(&mut self) {
         let now = chrono::Local::now();
         let current_date = now.format("%Y-%m-%d").to_string();
@@ -236,6 +275,37 @@ mod tests {
         assert_eq!(loaded.session.counters.urls_cleaned, 2);
     }
 
+    #[test]
+    fn primary_share_is_recorded_and_optional_on_load() {
+        let mut stats = Stats::default();
+        stats.increment(2, 2, 0, 1);
+        stats.increment_primary(1);
+        assert_eq!(stats.session.counters.primary_urls_cleaned, 1);
+        assert_eq!(stats.total.primary_urls_cleaned, 1);
+
+        // Stats files written before the counter existed still load.
+        let older = toml::to_string(&stats)
+            .unwrap()
+            .lines()
+            .filter(|l| !l.starts_with("primary_urls_cleaned"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let loaded: Stats = toml::from_str(&older).unwrap();
+        assert_eq!(loaded.total.urls_cleaned, 2);
+        assert_eq!(loaded.total.primary_urls_cleaned, 0);
+    }
+
+    #[test]
+    fn skips_are_counted_per_guard() {
+        let mut stats = Stats::default();
+        stats.increment_skip(Skip::TooLarge);
+        stats.increment_skip(Skip::Pattern);
+        stats.increment_skip(Skip::Pattern);
+        assert_eq!(stats.today.counters.skipped_too_large, 1);
+        assert_eq!(stats.total.skipped_by_pattern, 2);
+        assert_eq!(stats.session.counters.skipped_not_single_url, 0);
+    }
+
     #[test]
     fn day_rollover_resets_today_only() {
         let mut stats = Stats::default();